@@ -0,0 +1,870 @@
+use std::ffi::OsString;
+use std::io::{IsTerminal, Read, Write};
+use std::os::fd::{AsRawFd, OwnedFd};
+use std::os::unix::process::ExitStatusExt;
+use std::path::{Path, PathBuf};
+use std::pin::Pin;
+use std::process::{ExitStatus, Stdio};
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll, ready};
+use std::time::{Duration, Instant};
+
+use futures_util::FutureExt;
+use miette::IntoDiagnostic;
+use nix::fcntl::{FcntlArg, OFlag, fcntl};
+use nix::pty::{Winsize, openpty};
+use nix::sys::signal::{Signal, kill, killpg};
+use nix::sys::termios::{OutputFlags, SetArg, tcgetattr, tcsetattr};
+use nix::unistd::Pid;
+use serde::{Deserialize, Serialize};
+use tokio::io::unix::AsyncFd;
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt, ReadBuf};
+use tokio::signal::unix::{SignalKind, signal};
+
+use crate::config::NutConfig;
+use crate::ecosystem::Ecosystem;
+use crate::error::{NutError, Result};
+use crate::git::{self, GitCommand};
+use crate::hooks::{self, Hook};
+use crate::prompt::{self, Answer};
+use crate::runs::{Outcome, RepoRun, Run};
+use crate::workspace::Workspace;
+
+pub struct ApplyOptions {
+    /// Program and arguments to run in each repository
+    pub command: Vec<OsString>,
+    /// Only run in these repositories instead of all repositories in the workspace
+    pub repos: Option<Vec<PathBuf>>,
+    /// ID of the run this one repeats
+    pub rerun_of: Option<String>,
+    /// Conditions that must all hold in a repository, otherwise it is skipped
+    pub conditions: Vec<Condition>,
+    /// Recipe the command comes from, and the programs it requires
+    pub recipe: Option<String>,
+    pub requires: Vec<String>,
+    /// Stop the command in a repository after this time
+    pub timeout: Option<Duration>,
+    /// Ask the user before running, or before keeping the changes
+    pub confirm: Option<Confirm>,
+    /// Only write output to the log files, and show a progress line per repository
+    pub log_only: bool,
+    /// Directory for the log files instead of the run directory
+    pub log_dir: Option<PathBuf>,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Confirm {
+    /// Ask before running the command in each repository
+    BeforeRun,
+    /// Show the changes made by the command and ask whether to keep or revert them
+    AfterDiff,
+}
+
+/// Time a stopped command gets to exit before it is killed
+const GRACE_PERIOD: Duration = Duration::from_secs(10);
+
+/// Time to wait for the remaining output of a stopped command
+const OUTPUT_GRACE_PERIOD: Duration = Duration::from_secs(1);
+
+/// Why a command was stopped before it exited on its own
+#[derive(Clone, Copy)]
+enum Stop {
+    TimedOut,
+    /// Ctrl-C, which only cancels the current repository
+    Interrupted,
+    /// SIGTERM or SIGHUP, which abort the whole run
+    Terminated,
+}
+
+/// Signals received by nut while running commands, to be forwarded to them
+struct Signals {
+    interrupt: tokio::signal::unix::Signal,
+    terminate: tokio::signal::unix::Signal,
+    hangup: tokio::signal::unix::Signal,
+}
+
+impl Signals {
+    fn new() -> std::io::Result<Self> {
+        Ok(Self {
+            interrupt: signal(SignalKind::interrupt())?,
+            terminate: signal(SignalKind::terminate())?,
+            hangup: signal(SignalKind::hangup())?,
+        })
+    }
+
+    /// A signal received since the last call, without waiting for one
+    fn pending(&mut self) -> Option<Stop> {
+        let mut stop = None;
+        while self.interrupt.recv().now_or_never().is_some() {
+            stop = Some(Stop::Interrupted);
+        }
+        while self.terminate.recv().now_or_never().is_some()
+            || self.hangup.recv().now_or_never().is_some()
+        {
+            stop = Some(Stop::Terminated);
+        }
+        stop
+    }
+
+    async fn recv(&mut self) -> Stop {
+        tokio::select! {
+            _ = self.interrupt.recv() => Stop::Interrupted,
+            _ = self.terminate.recv() => Stop::Terminated,
+            _ = self.hangup.recv() => Stop::Terminated,
+        }
+    }
+}
+
+/// Parse durations like `90`, `90s`, `5m` or `1h`
+pub fn parse_duration(value: &str) -> std::result::Result<Duration, String> {
+    let (number, unit) = match value.find(|c: char| !c.is_ascii_digit()) {
+        Some(index) => value.split_at(index),
+        None => (value, "s"),
+    };
+    let number: u64 = number
+        .parse()
+        .map_err(|_| format!("invalid duration '{value}'"))?;
+    let multiplier = match unit {
+        "s" => 1,
+        "m" => 60,
+        "h" => 60 * 60,
+        _ => return Err(format!("invalid unit in '{value}', use s, m or h")),
+    };
+    let seconds = number
+        .checked_mul(multiplier)
+        .ok_or_else(|| format!("duration '{value}' is too long"))?;
+    Ok(Duration::from_secs(seconds))
+}
+
+/// Per-repository predicate, evaluated before the command
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Condition {
+    /// Shell command that must exit successfully
+    Command(String),
+    /// Path, relative to the repository, that must exist
+    File(PathBuf),
+    /// The repository must belong to one of these ecosystems
+    Ecosystem(Vec<Ecosystem>),
+}
+
+impl Condition {
+    async fn holds(&self, repo_dir: &Path, env: &[(&str, OsString)]) -> bool {
+        match self {
+            Condition::Command(command) => tokio::process::Command::new("sh")
+                .arg("-c")
+                .arg(command)
+                .current_dir(repo_dir)
+                .envs(env.iter().map(|(k, v)| (k, v)))
+                .stdin(Stdio::null())
+                .stdout(Stdio::null())
+                .stderr(Stdio::null())
+                .status()
+                .await
+                .is_ok_and(|s| s.success()),
+            Condition::File(path) => tokio::fs::try_exists(repo_dir.join(path))
+                .await
+                .unwrap_or(false),
+            Condition::Ecosystem(ecosystems) => Ecosystem::detect_all(repo_dir)
+                .iter()
+                .any(|e| ecosystems.contains(e)),
+        }
+    }
+}
+
+impl std::fmt::Display for Condition {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Condition::Command(command) => write!(f, "{command}"),
+            Condition::File(path) => write!(f, "{} exists", path.display()),
+            Condition::Ecosystem(ecosystems) => {
+                let names: Vec<_> = ecosystems.iter().map(|e| e.name()).collect();
+                write!(f, "ecosystem is {}", names.join(" or "))
+            }
+        }
+    }
+}
+
+/// Resolve the path of a script to run with apply, which must be executable
+pub async fn resolve_script(script_path: &Path) -> Result<PathBuf> {
+    let absolute_script_path =
+        tokio::fs::canonicalize(script_path)
+            .await
+            .map_err(|e| NutError::ScriptPathInvalid {
+                path: script_path.display().to_string(),
+                source: e,
+            })?;
+
+    // only for unix
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        let metadata = tokio::fs::metadata(&absolute_script_path)
+            .await
+            .map_err(|e| NutError::ScriptPathInvalid {
+                path: script_path.display().to_string(),
+                source: e,
+            })?;
+        let permissions = metadata.permissions();
+        if (permissions.mode() & 0o111) == 0 {
+            return Err(NutError::ScriptNotExecutable {
+                path: script_path.display().to_string(),
+            });
+        }
+    }
+
+    Ok(absolute_script_path)
+}
+
+/// Command running `command` with the configured shell, or `$SHELL` if none is configured
+pub fn shell_command(command: &str) -> Result<Vec<OsString>> {
+    let shell = match NutConfig::load()?.shell {
+        Some(shell) => shell.into_os_string(),
+        None => std::env::var_os("SHELL")
+            .filter(|s| !s.is_empty())
+            .unwrap_or_else(|| OsString::from("sh")),
+    };
+    Ok(vec![shell, OsString::from("-c"), OsString::from(command)])
+}
+
+/// Execute a command in each repository without using a subshell.
+///
+/// The command sees the workspace and repository in the `NUT_WORKSPACE_ID`,
+/// `NUT_WORKSPACE_DIR`, `NUT_REPO` and `NUT_REPO_DIR` environment variables.
+///
+/// Output is shown while the command runs, and also captured to a file per
+/// repository. The results are stored as a run in the workspace.
+///
+/// Commands get the terminal's stdin, unless it is needed for confirmations or the
+/// output goes to log files only. With a timeout, they run without stdin in their own
+/// process group, so they can be stopped with all their children. After Ctrl-C, the
+/// user decides whether to continue with the remaining repositories.
+pub async fn apply(workspace: &Workspace, options: ApplyOptions) -> Result<Run> {
+    let repos = match options.repos {
+        Some(repos) => repos,
+        None => git::find_repositories(&workspace.path)?,
+    };
+
+    let mut run = Run::new(
+        options
+            .command
+            .iter()
+            .map(|a| a.to_string_lossy().to_string())
+            .collect(),
+        options.rerun_of,
+    );
+    run.log_dir = options.log_dir;
+    run.recipe = options.recipe;
+    run.requires = options.requires;
+    run.conditions = options.conditions;
+
+    if repos.is_empty() {
+        println!("No repositories found in workspace");
+        return Ok(run);
+    }
+
+    let run_env = [("NUT_RUN_ID", OsString::from(&run.id))];
+    hooks::run(Hook::PreApply, workspace, &workspace.path, &run_env).await?;
+    // the output is written next to the run, which is listed as soon as it exists
+    run.save(&workspace.path)?;
+
+    let mut signals = Signals::new().map_err(|e| NutError::CommandFailed {
+        repo: workspace.path.display().to_string(),
+        source: e,
+    })?;
+    let mut stdin = tokio::io::BufReader::new(tokio::io::stdin()).lines();
+    let command_line = run.command.join(" ");
+    // on a terminal, commands write to a pseudo terminal so they keep colors and progress bars
+    let on_terminal =
+        !options.log_only && std::io::stdout().is_terminal() && std::io::stderr().is_terminal();
+    // set once the user answered "a" to a confirmation prompt
+    let mut confirm_all = false;
+
+    let mut repos = repos.into_iter();
+    while let Some(repo_path_relative) = repos.next() {
+        if options.log_only {
+            print!("{}: ", repo_path_relative.display());
+            std::io::stdout().flush().ok();
+        } else {
+            println!("==> {} <==", repo_path_relative.display());
+        }
+
+        let repo_dir = workspace.path.join(&repo_path_relative);
+        let env = [
+            ("NUT_WORKSPACE_ID", OsString::from(workspace.id.to_string())),
+            ("NUT_WORKSPACE_DIR", workspace.path.clone().into_os_string()),
+            ("NUT_REPO", repo_path_relative.clone().into_os_string()),
+            ("NUT_REPO_DIR", repo_dir.clone().into_os_string()),
+        ];
+        let unmet = unmet_condition(&run.conditions, &repo_dir, &env).await;
+        // Ctrl-C also reaches condition commands, which then fail
+        if let Some(stop) = signals.pending() {
+            println!("Interrupted");
+            run.repos.push(RepoRun::not_started(
+                &repo_path_relative,
+                Outcome::NotRun,
+                "not run, interrupted",
+            ));
+            if should_abort(stop, repos.len(), &mut stdin, &mut signals).await? {
+                abort_remaining(&mut run, &mut repos);
+            }
+            run.save(&workspace.path)?;
+            continue;
+        }
+        let skip_message = if let Some(condition) = unmet {
+            Some(format!("condition not met: {condition}"))
+        } else if options.confirm == Some(Confirm::AfterDiff) && has_changes(&repo_dir).await? {
+            // reverting would discard these changes as well
+            Some("repository has uncommitted changes".to_string())
+        } else {
+            None
+        };
+        if let Some(message) = skip_message {
+            println!("Skipped, {message}");
+            if !options.log_only {
+                println!();
+            }
+            run.repos.push(RepoRun::not_started(
+                &repo_path_relative,
+                Outcome::Skipped,
+                message,
+            ));
+            run.save(&workspace.path)?;
+            continue;
+        }
+
+        if options.confirm == Some(Confirm::BeforeRun) && !confirm_all {
+            let question = format!("Run '{command_line}' here?");
+            let answer = ask(&question, &mut stdin, &mut signals).await?;
+            if let Answer::All = answer {
+                confirm_all = true;
+            }
+            if let Answer::No | Answer::Quit = answer {
+                println!();
+                run.repos.push(RepoRun::not_started(
+                    &repo_path_relative,
+                    Outcome::Skipped,
+                    "skipped by user",
+                ));
+                if let Answer::Quit = answer {
+                    for repo in repos.by_ref() {
+                        run.repos.push(RepoRun::not_started(
+                            &repo,
+                            Outcome::Skipped,
+                            "skipped by user",
+                        ));
+                    }
+                }
+                run.save(&workspace.path)?;
+                continue;
+            }
+        }
+
+        // commits made by the command are reverted along with the other changes
+        let original_head = match options.confirm {
+            Some(Confirm::AfterDiff) => head_commit(&repo_dir).await,
+            _ => None,
+        };
+
+        let output_path = run.output_path(&workspace.path, &repo_path_relative);
+        let started = Instant::now();
+        let (status, stop) = run_in_repo(
+            &repo_dir,
+            &options.command,
+            &env,
+            &output_path,
+            !options.log_only,
+            on_terminal,
+            // confirmations read from stdin, and prompts would not be visible in log-only mode
+            !options.log_only && options.confirm.is_none(),
+            options.timeout,
+            &mut signals,
+        )
+        .await
+        .map_err(|e| NutError::CommandFailed {
+            repo: repo_path_relative.display().to_string(),
+            source: e,
+        })?;
+
+        let error = match stop {
+            Some(Stop::TimedOut) => Some(format!(
+                "Timed out after {}s",
+                options.timeout.unwrap_or_default().as_secs()
+            )),
+            Some(Stop::Interrupted | Stop::Terminated) => Some("Cancelled".to_string()),
+            None => (!status.success()).then(|| describe_exit_status(&status)),
+        };
+        let duration = started.elapsed();
+        if options.log_only {
+            match &error {
+                Some(message) => println!(
+                    "failed after {:.1}s ({message}), see {}",
+                    duration.as_secs_f64(),
+                    output_path.display()
+                ),
+                None => println!("ok after {:.1}s", duration.as_secs_f64()),
+            }
+        } else if let Some(message) = &error {
+            // render the error using miette
+            let error: miette::Result<()> = Err(NutError::CommandFailed {
+                repo: repo_path_relative.display().to_string(),
+                source: std::io::Error::other(message.clone()),
+            })
+            .into_diagnostic();
+
+            // this will automatically render fancy miette errors due to global hook in main.rs
+            eprintln!();
+            eprintln!("{:?}", error.err().unwrap());
+        }
+        if !options.log_only {
+            println!();
+        }
+
+        let mut message = error.clone();
+        let mut quit = false;
+        if options.confirm == Some(Confirm::AfterDiff)
+            && stop.is_none()
+            && (has_changes(&repo_dir).await? || head_commit(&repo_dir).await != original_head)
+        {
+            print_changes(&repo_dir, original_head.as_deref()).await?;
+            let answer = if confirm_all {
+                Answer::Yes
+            } else {
+                ask("Keep the changes?", &mut stdin, &mut signals).await?
+            };
+            match answer {
+                Answer::Yes => {}
+                Answer::All => confirm_all = true,
+                Answer::No | Answer::Quit => {
+                    revert(&repo_dir, original_head.as_deref()).await?;
+                    println!("Changes reverted");
+                    println!();
+                    message = Some(match &error {
+                        Some(error) => format!("{error}, changes reverted"),
+                        None => "changes reverted".to_string(),
+                    });
+                    quit = matches!(answer, Answer::Quit);
+                }
+            }
+        }
+
+        run.repos.push(RepoRun {
+            repo: repo_path_relative.to_string_lossy().to_string(),
+            outcome: if error.is_none() {
+                Outcome::Success
+            } else {
+                Outcome::Failed
+            },
+            exit_code: status.code(),
+            message,
+            duration_ms: duration.as_millis() as u64,
+        });
+        if quit {
+            for repo in repos.by_ref() {
+                run.repos.push(RepoRun::not_started(
+                    &repo,
+                    Outcome::Skipped,
+                    "skipped by user",
+                ));
+            }
+        }
+        // save after every repository, so interrupted runs are recorded as well
+        run.save(&workspace.path)?;
+
+        if let Some(stop) = stop
+            && should_abort(stop, repos.len(), &mut stdin, &mut signals).await?
+        {
+            abort_remaining(&mut run, &mut repos);
+            run.save(&workspace.path)?;
+        }
+    }
+
+    if options.log_only {
+        let log_dir = match &run.log_dir {
+            Some(log_dir) => log_dir.clone(),
+            None => run.dir(&workspace.path).join("output"),
+        };
+        println!("Output written to {}", log_dir.display());
+    }
+    println!("Run {}: {}", run.id, run.summary());
+    let not_run: Vec<_> = run
+        .repos
+        .iter()
+        .filter(|r| r.outcome == Outcome::NotRun)
+        .map(|r| r.repo.as_str())
+        .collect();
+    if !not_run.is_empty() {
+        println!("Not run: {}", not_run.join(", "));
+    }
+    if !run.failed_repos().is_empty() {
+        println!(
+            "Re-run the failed repositories with: nut apply --rerun-failed {}",
+            run.id
+        );
+    }
+
+    Ok(run)
+}
+
+/// Whether to stop after a signal: always for SIGTERM and SIGHUP, after Ctrl-C unless
+/// the user wants to continue with the remaining repositories
+async fn should_abort(
+    stop: Stop,
+    remaining: usize,
+    stdin: &mut tokio::io::Lines<tokio::io::BufReader<tokio::io::Stdin>>,
+    signals: &mut Signals,
+) -> Result<bool> {
+    Ok(match stop {
+        Stop::Interrupted if remaining > 0 => {
+            let question = format!("Continue with the remaining {remaining} repositories?");
+            !matches!(
+                ask(&question, stdin, signals).await?,
+                Answer::Yes | Answer::All
+            )
+        }
+        Stop::TimedOut => false,
+        _ => true,
+    })
+}
+
+fn abort_remaining(run: &mut Run, repos: &mut impl Iterator<Item = PathBuf>) {
+    for repo in repos {
+        run.repos.push(RepoRun::not_started(
+            &repo,
+            Outcome::NotRun,
+            "not run, apply was aborted",
+        ));
+    }
+    run.aborted = true;
+}
+
+pub fn describe_exit_status(status: &ExitStatus) -> String {
+    if let Some(code) = status.code() {
+        format!("Command exited with status code {}", code)
+    } else if let Some(signal) = status.signal() {
+        format!("Command terminated by signal {}", signal)
+    } else {
+        "Command terminated for unknown reason".to_string()
+    }
+}
+
+async fn unmet_condition<'a>(
+    conditions: &'a [Condition],
+    repo_dir: &Path,
+    env: &[(&str, OsString)],
+) -> Option<&'a Condition> {
+    for condition in conditions {
+        if !condition.holds(repo_dir, env).await {
+            return Some(condition);
+        }
+    }
+    None
+}
+
+/// Ask a question on the terminal. End of input and Ctrl-C count as quitting
+async fn ask(
+    question: &str,
+    stdin: &mut tokio::io::Lines<tokio::io::BufReader<tokio::io::Stdin>>,
+    signals: &mut Signals,
+) -> Result<Answer> {
+    prompt::ask(question, async || {
+        tokio::select! {
+            line = stdin.next_line() => {
+                line.map_err(|e| NutError::ReadStdinFailed { source: e })
+            }
+            _ = signals.recv() => Ok(None),
+        }
+    })
+    .await
+}
+
+async fn has_changes(repo_dir: &Path) -> Result<bool> {
+    let status = GitCommand::new(repo_dir)
+        .args(&["status", "--porcelain"])
+        .checked_output()
+        .await?;
+    Ok(!status.is_empty())
+}
+
+async fn head_commit(repo_dir: &Path) -> Option<String> {
+    GitCommand::new(repo_dir)
+        .args(&["rev-parse", "--verify", "--quiet", "HEAD"])
+        .checked_output_string()
+        .await
+        .ok()
+}
+
+/// Print the new commits, `git diff --stat` against the original HEAD and the new
+/// untracked files
+async fn print_changes(repo_dir: &Path, original_head: Option<&str>) -> Result<()> {
+    let original_head = original_head.unwrap_or("HEAD");
+    let range = format!("{original_head}..HEAD");
+    let commits = GitCommand::new(repo_dir)
+        .args(&["log", "--oneline", &range])
+        .checked_output_string()
+        .await?;
+    for commit in commits.lines() {
+        println!(" commit {commit}");
+    }
+    let stat = GitCommand::new(repo_dir)
+        .args(&["diff", "--stat", original_head])
+        .checked_output_string()
+        .await?;
+    if !stat.is_empty() {
+        println!("{stat}");
+    }
+    let untracked = GitCommand::new(repo_dir)
+        .args(&["ls-files", "--others", "--exclude-standard"])
+        .checked_output_string()
+        .await?;
+    for file in untracked.lines() {
+        println!(" {file} (new)");
+    }
+    Ok(())
+}
+
+/// Discard all changes and commits since the original HEAD, repositories are only run
+/// in when they have no uncommitted changes
+async fn revert(repo_dir: &Path, original_head: Option<&str>) -> Result<()> {
+    GitCommand::new(repo_dir)
+        .args(&[
+            "reset",
+            "--quiet",
+            "--hard",
+            original_head.unwrap_or("HEAD"),
+        ])
+        .run()
+        .await?;
+    GitCommand::new(repo_dir)
+        .args(&["clean", "--force", "-d", "--quiet"])
+        .run()
+        .await
+}
+
+/// Run the command. Its stdout and stderr are copied to the `log` file, and to the
+/// terminal if `echo` is set. With `pty`, the command writes to a pseudo terminal instead
+/// of pipes, so it behaves like it does on a terminal.
+///
+/// The command is stopped when it exceeds the timeout or nut receives a signal. With a
+/// timeout it gets its own process group and no stdin, as it would be stopped by the
+/// terminal when reading from it in the background.
+#[allow(clippy::too_many_arguments)]
+async fn run_in_repo(
+    repo_dir: &Path,
+    command: &[OsString],
+    env: &[(&str, OsString)],
+    log: &Path,
+    echo: bool,
+    pty: bool,
+    interactive: bool,
+    timeout: Option<Duration>,
+    signals: &mut Signals,
+) -> std::io::Result<(ExitStatus, Option<Stop>)> {
+    if let Some(parent) = log.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    let log = Arc::new(Mutex::new(std::fs::File::create(log)?));
+
+    let own_process_group = timeout.is_some();
+    let mut process = tokio::process::Command::new(&command[0]);
+    process
+        .args(&command[1..])
+        .current_dir(repo_dir)
+        .envs(env.iter().map(|(k, v)| (k, v)));
+    // fall back to pipes if no pseudo terminal is available
+    let pty = match pty {
+        true => open_pty().ok(),
+        false => None,
+    };
+    match &pty {
+        Some((_, slave)) => {
+            process
+                .stdout(slave.try_clone()?)
+                .stderr(slave.try_clone()?);
+        }
+        None => {
+            process.stdout(Stdio::piped()).stderr(Stdio::piped());
+        }
+    }
+    if own_process_group {
+        process.process_group(0);
+    }
+    if own_process_group || !interactive {
+        process.stdin(Stdio::null());
+    }
+    let mut child = process.spawn()?;
+    let pid = child.id().map(|id| Pid::from_raw(id as i32));
+    // the pseudo terminal reports the end of the output once no process has it open,
+    // so the copies of its other side are closed
+    drop(process);
+
+    let tasks = match pty {
+        Some((master, slave)) => {
+            drop(slave);
+            let log = log.clone();
+            vec![tokio::spawn(async move {
+                tee(master, echo.then(tokio::io::stdout), log).await
+            })]
+        }
+        None => {
+            let stdout = child.stdout.take().map(|out| {
+                let log = log.clone();
+                tokio::spawn(async move { tee(out, echo.then(tokio::io::stdout), log).await })
+            });
+            let stderr = child.stderr.take().map(|err| {
+                let log = log.clone();
+                tokio::spawn(async move { tee(err, echo.then(tokio::io::stderr), log).await })
+            });
+            [stdout, stderr].into_iter().flatten().collect()
+        }
+    };
+
+    let deadline = async {
+        match timeout {
+            Some(timeout) => tokio::time::sleep(timeout).await,
+            None => std::future::pending().await,
+        }
+    };
+    let (status, stop) = tokio::select! {
+        status = child.wait() => (status?, None),
+        _ = deadline => (stop_child(&mut child, pid, own_process_group, Signal::SIGTERM).await?, Some(Stop::TimedOut)),
+        stop = signals.recv() => {
+            let signal = match stop {
+                Stop::Interrupted => Signal::SIGINT,
+                _ => Signal::SIGTERM,
+            };
+            (stop_child(&mut child, pid, own_process_group, signal).await?, Some(stop))
+        }
+    };
+
+    for task in tasks {
+        // processes started by a stopped command may keep the output open, and only
+        // receive the signal when they share its process group
+        if stop.is_some() && !own_process_group {
+            match tokio::time::timeout(OUTPUT_GRACE_PERIOD, task).await {
+                Ok(result) => result.map_err(std::io::Error::other)??,
+                Err(_) => continue,
+            }
+        } else {
+            task.await.map_err(std::io::Error::other)??;
+        }
+    }
+
+    Ok((status, stop))
+}
+
+/// Send `signal` to the child, or its process group, and kill it if it does not exit
+/// within the grace period
+async fn stop_child(
+    child: &mut tokio::process::Child,
+    pid: Option<Pid>,
+    own_process_group: bool,
+    signal: Signal,
+) -> std::io::Result<ExitStatus> {
+    let Some(pid) = pid else {
+        return child.wait().await;
+    };
+    let send = |signal| match own_process_group {
+        true => killpg(pid, signal),
+        false => kill(pid, signal),
+    };
+    // in the terminal's foreground process group, the command got Ctrl-C already
+    if own_process_group || signal != Signal::SIGINT {
+        send(signal).ok();
+    }
+    if let Ok(status) = tokio::time::timeout(GRACE_PERIOD, child.wait()).await {
+        return status;
+    }
+    send(Signal::SIGKILL).ok();
+    child.wait().await
+}
+
+/// Open a pseudo terminal with the size of the terminal of nut. Returns the master side,
+/// which the output is read from, and the side for the command
+fn open_pty() -> std::io::Result<(PtyMaster, OwnedFd)> {
+    nix::ioctl_read_bad!(terminal_size, nix::libc::TIOCGWINSZ, Winsize);
+    let mut size = Winsize {
+        ws_row: 0,
+        ws_col: 0,
+        ws_xpixel: 0,
+        ws_ypixel: 0,
+    };
+    // SAFETY: TIOCGWINSZ only writes a winsize struct
+    let size = unsafe { terminal_size(std::io::stdout().as_raw_fd(), &mut size) }
+        .ok()
+        .map(|_| size);
+
+    let pty = openpty(size.as_ref(), None)?;
+    // keep newlines as they are in the log, the terminal of nut translates them
+    let mut termios = tcgetattr(&pty.slave)?;
+    termios.output_flags.remove(OutputFlags::OPOST);
+    tcsetattr(&pty.slave, SetArg::TCSANOW, &termios)?;
+    fcntl(&pty.master, FcntlArg::F_SETFL(OFlag::O_NONBLOCK))?;
+    let master = PtyMaster(AsyncFd::new(std::fs::File::from(pty.master))?);
+    Ok((master, pty.slave))
+}
+
+/// Master side of a pseudo terminal. Reading fails with EIO instead of returning end of
+/// file once the command and everything it started closed the other side
+struct PtyMaster(AsyncFd<std::fs::File>);
+
+impl AsyncRead for PtyMaster {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<std::io::Result<()>> {
+        loop {
+            let mut guard = ready!(self.0.poll_read_ready(cx))?;
+            let unfilled = buf.initialize_unfilled();
+            match guard.try_io(|fd| fd.get_ref().read(unfilled)) {
+                Ok(Ok(n)) => {
+                    buf.advance(n);
+                    return Poll::Ready(Ok(()));
+                }
+                Ok(Err(e)) if e.raw_os_error() == Some(nix::libc::EIO) => {
+                    return Poll::Ready(Ok(()));
+                }
+                Ok(Err(e)) => return Poll::Ready(Err(e)),
+                Err(_would_block) => continue,
+            }
+        }
+    }
+}
+
+async fn tee(
+    mut reader: impl AsyncRead + Unpin,
+    mut terminal: Option<impl AsyncWrite + Unpin>,
+    log: Arc<Mutex<std::fs::File>>,
+) -> std::io::Result<()> {
+    let mut buffer = [0u8; 8192];
+    loop {
+        let n = reader.read(&mut buffer).await?;
+        if n == 0 {
+            return Ok(());
+        }
+        if let Some(terminal) = &mut terminal {
+            terminal.write_all(&buffer[..n]).await?;
+            terminal.flush().await?;
+        }
+        log.lock()
+            .map_err(|_| std::io::Error::other("output log lock poisoned"))?
+            .write_all(&buffer[..n])?;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parses_durations() {
+        assert_eq!(parse_duration("90"), Ok(Duration::from_secs(90)));
+        assert_eq!(parse_duration("90s"), Ok(Duration::from_secs(90)));
+        assert_eq!(parse_duration("5m"), Ok(Duration::from_secs(300)));
+        assert_eq!(parse_duration("1h"), Ok(Duration::from_secs(3600)));
+        assert!(parse_duration("1d").is_err());
+        assert!(parse_duration("m").is_err());
+        assert!(parse_duration("99999999999999999h").is_err());
+    }
+}
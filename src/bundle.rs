@@ -0,0 +1,461 @@
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+use ulid::Ulid;
+
+use crate::error::{NutError, Result};
+use crate::git::{self, GitCommand};
+use crate::gitconfig;
+use crate::hooks;
+use crate::workspace::Workspace;
+
+const MANIFEST_VERSION: u32 = 1;
+const MANIFEST_FILE: &str = "manifest.json";
+
+#[derive(Serialize, Deserialize)]
+struct Manifest {
+    version: u32,
+    name: Option<String>,
+    description: Option<String>,
+    tags: Vec<String>,
+    repos: Vec<RepoManifest>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct RepoManifest {
+    /// Path relative to the workspace, which is also the full repository name
+    path: String,
+    default_branch: Option<String>,
+    default_branch_commit: Option<String>,
+    head_branch: Option<String>,
+    head_commit: String,
+    /// Local branches and the commits they point to
+    branches: BTreeMap<String, String>,
+    /// Upstream of local branches that have one, e.g. `origin/main`
+    upstreams: BTreeMap<String, String>,
+    has_bundle: bool,
+    has_staged_patch: bool,
+    has_unstaged_patch: bool,
+    untracked_files: Vec<String>,
+}
+
+/// Export a workspace into a zstd compressed tarball.
+///
+/// Only commits that are not on any remote are bundled, everything else is
+/// restored from the cache when importing.
+pub async fn export(workspace: &Workspace, output: &Path) -> Result<()> {
+    let staging_dir = create_staging_dir("export")?;
+    let result = export_to(workspace, &staging_dir, output).await;
+    std::fs::remove_dir_all(&staging_dir).ok();
+    result
+}
+
+/// Create a new workspace from a bundle created by `export`
+pub async fn import(bundle: &Path) -> Result<Workspace> {
+    let staging_dir = create_staging_dir("import")?;
+    let result = import_from(bundle, &staging_dir).await;
+    std::fs::remove_dir_all(&staging_dir).ok();
+    result
+}
+
+async fn export_to(workspace: &Workspace, staging_dir: &Path, output: &Path) -> Result<()> {
+    let mut repos = Vec::new();
+    for repo_path_relative in git::find_repositories(&workspace.path)? {
+        let path = repo_path_relative
+            .to_str()
+            .ok_or(NutError::InvalidUtf8)?
+            .to_string();
+        println!("{path}");
+        let repo_dir = workspace.path.join(&repo_path_relative);
+        let repo_staging_dir = staging_dir.join("repos").join(&repo_path_relative);
+        create_dir(&repo_staging_dir)?;
+        match export_repo(&repo_dir, &repo_staging_dir, path).await? {
+            Some(repo) => repos.push(repo),
+            None => println!("  skipped, repository has no commits"),
+        }
+    }
+
+    let manifest = Manifest {
+        version: MANIFEST_VERSION,
+        name: workspace.name().await,
+        description: workspace.description().await,
+        tags: workspace.tags().await,
+        repos,
+    };
+    let manifest_path = staging_dir.join(MANIFEST_FILE);
+    let json = serde_json::to_vec_pretty(&manifest).map_err(|e| NutError::SerializeFailed {
+        source: Box::new(e),
+    })?;
+    write_file(&manifest_path, &json)?;
+
+    let (staging_dir, archive) = (staging_dir.to_path_buf(), output.to_path_buf());
+    tokio::task::spawn_blocking(move || write_archive(&staging_dir, &archive))
+        .await
+        .map_err(std::io::Error::other)
+        .flatten()
+        .map_err(|e| NutError::BundleArchiveFailed {
+            path: output.to_path_buf(),
+            source: e,
+        })
+}
+
+async fn export_repo(
+    repo_dir: &Path,
+    staging_dir: &Path,
+    path: String,
+) -> Result<Option<RepoManifest>> {
+    let Ok(head_commit) = GitCommand::new(repo_dir)
+        .args(&["rev-parse", "--verify", "HEAD"])
+        .checked_output_string()
+        .await
+    else {
+        return Ok(None);
+    };
+
+    let head_branch = Some(
+        GitCommand::new(repo_dir)
+            .args(&["branch", "--show-current"])
+            .output_string()
+            .await?,
+    )
+    .filter(|b| !b.is_empty());
+
+    let default_branch = git::remote_default_branch(repo_dir).await;
+    let default_branch_commit = match &default_branch {
+        Some(branch) => Some(
+            GitCommand::new(repo_dir)
+                .args(&["rev-parse", &format!("origin/{branch}")])
+                .output_string()
+                .await?,
+        )
+        .filter(|c| !c.is_empty()),
+        None => None,
+    };
+
+    let mut branches = BTreeMap::new();
+    let mut upstreams = BTreeMap::new();
+    let refs = GitCommand::new(repo_dir)
+        .args(&[
+            "for-each-ref",
+            "--format=%(refname:short) %(objectname) %(upstream:short)",
+            "refs/heads",
+        ])
+        .checked_output()
+        .await?;
+    for line in String::from_utf8_lossy(&refs).lines() {
+        let mut parts = line.split_whitespace();
+        if let (Some(branch), Some(commit)) = (parts.next(), parts.next()) {
+            branches.insert(branch.to_string(), commit.to_string());
+            if let Some(upstream) = parts.next() {
+                upstreams.insert(branch.to_string(), upstream.to_string());
+            }
+        }
+    }
+
+    // Bundle only what is not available on the remote
+    let unpushed = GitCommand::new(repo_dir)
+        .args(&[
+            "rev-list",
+            "--count",
+            "--branches",
+            "HEAD",
+            "--not",
+            "--remotes",
+        ])
+        .output_string()
+        .await?;
+    let has_bundle = unpushed.parse::<usize>().unwrap_or(0) > 0;
+    if has_bundle {
+        let bundle_path = staging_dir.join("commits.bundle");
+        GitCommand::new(repo_dir)
+            .args(&[
+                "bundle",
+                "create",
+                bundle_path.to_str().ok_or(NutError::InvalidUtf8)?,
+                "--branches",
+                "HEAD",
+                "--not",
+                "--remotes",
+            ])
+            .checked_output()
+            .await?;
+    }
+
+    let staged = GitCommand::new(repo_dir)
+        .args(&["diff", "--cached", "--binary"])
+        .checked_output()
+        .await?;
+    if !staged.is_empty() {
+        write_file(&staging_dir.join("staged.patch"), &staged)?;
+    }
+
+    let unstaged = GitCommand::new(repo_dir)
+        .args(&["diff", "--binary"])
+        .checked_output()
+        .await?;
+    if !unstaged.is_empty() {
+        write_file(&staging_dir.join("unstaged.patch"), &unstaged)?;
+    }
+
+    let untracked = GitCommand::new(repo_dir)
+        .args(&["ls-files", "--others", "--exclude-standard", "-z"])
+        .checked_output()
+        .await?;
+    let untracked_files: Vec<String> = String::from_utf8_lossy(&untracked)
+        .split('\0')
+        .filter(|f| !f.is_empty())
+        .map(|f| f.to_string())
+        .collect();
+    for file in &untracked_files {
+        copy_file(
+            &repo_dir.join(file),
+            &staging_dir.join("untracked").join(file),
+        )?;
+    }
+
+    Ok(Some(RepoManifest {
+        path,
+        default_branch,
+        default_branch_commit,
+        head_branch,
+        head_commit,
+        branches,
+        upstreams,
+        has_bundle,
+        has_staged_patch: !staged.is_empty(),
+        has_unstaged_patch: !unstaged.is_empty(),
+        untracked_files,
+    }))
+}
+
+async fn import_from(bundle: &Path, staging_dir: &Path) -> Result<Workspace> {
+    let (archive, staging) = (bundle.to_path_buf(), staging_dir.to_path_buf());
+    tokio::task::spawn_blocking(move || read_archive(&archive, &staging))
+        .await
+        .map_err(std::io::Error::other)
+        .flatten()
+        .map_err(|e| NutError::BundleArchiveFailed {
+            path: bundle.to_path_buf(),
+            source: e,
+        })?;
+
+    let manifest_path = staging_dir.join(MANIFEST_FILE);
+    let json = std::fs::read(&manifest_path).map_err(|_| NutError::InvalidBundle {
+        path: bundle.to_path_buf(),
+        reason: format!("{MANIFEST_FILE} is missing"),
+    })?;
+    let manifest: Manifest =
+        serde_json::from_slice(&json).map_err(|e| NutError::InvalidBundle {
+            path: bundle.to_path_buf(),
+            reason: e.to_string(),
+        })?;
+    if manifest.version != MANIFEST_VERSION {
+        return Err(NutError::InvalidBundle {
+            path: bundle.to_path_buf(),
+            reason: format!("unsupported bundle version {}", manifest.version),
+        });
+    }
+
+    for repo in &manifest.repos {
+        let paths = std::iter::once(&repo.path).chain(&repo.untracked_files);
+        if let Some(path) = paths.into_iter().find(|p| !is_plain_relative(p)) {
+            return Err(NutError::InvalidBundle {
+                path: bundle.to_path_buf(),
+                reason: format!("path '{path}' is not inside the workspace"),
+            });
+        }
+    }
+
+    let workspace = Workspace::create(manifest.description.as_deref().unwrap_or_default()).await?;
+    if let Err(e) = restore_workspace(&workspace, &manifest, staging_dir).await {
+        // don't leave a half restored workspace behind
+        std::fs::remove_dir_all(&workspace.path).ok();
+        return Err(e);
+    }
+    Ok(workspace)
+}
+
+async fn restore_workspace(
+    workspace: &Workspace,
+    manifest: &Manifest,
+    staging_dir: &Path,
+) -> Result<()> {
+    workspace.set_tags(&manifest.tags).await?;
+    if let Some(name) = &manifest.name {
+        if Workspace::validate_name(name, Some(workspace.id))
+            .await
+            .is_ok()
+        {
+            workspace.set_name(name).await?;
+        } else {
+            println!("Name '{name}' is already taken, the workspace will be unnamed");
+        }
+    }
+
+    let overrides = gitconfig::overrides(workspace).await?;
+    for repo in &manifest.repos {
+        println!("{}", repo.path);
+        git::clone(
+            &workspace.path,
+            &repo.path,
+            &repo.default_branch_commit,
+            &repo.default_branch,
+        )
+        .await?;
+        restore_repo(
+            &workspace.path.join(&repo.path),
+            &staging_dir.join("repos").join(&repo.path),
+            repo,
+        )
+        .await?;
+        gitconfig::apply(&workspace.path.join(&repo.path), &overrides).await?;
+        hooks::post_import(workspace, Path::new(&repo.path)).await?;
+    }
+
+    Ok(())
+}
+
+/// Whether a path from a manifest is relative and stays below the directory it is joined to
+fn is_plain_relative(path: &str) -> bool {
+    !path.is_empty()
+        && Path::new(path)
+            .components()
+            .all(|c| matches!(c, std::path::Component::Normal(_)))
+}
+
+async fn restore_repo(repo_dir: &Path, staging_dir: &Path, repo: &RepoManifest) -> Result<()> {
+    if repo.has_bundle {
+        let bundle_path = staging_dir.join("commits.bundle");
+        let bundle_path = bundle_path.to_str().ok_or(NutError::InvalidUtf8)?;
+        let heads = GitCommand::new(repo_dir)
+            .args(&["bundle", "list-heads", bundle_path])
+            .checked_output()
+            .await?;
+        let refspecs: Vec<String> = String::from_utf8_lossy(&heads)
+            .lines()
+            .filter_map(|line| line.split_whitespace().nth(1))
+            .enumerate()
+            .map(|(i, name)| format!("{name}:refs/nut/bundle/{i}"))
+            .collect();
+        let mut fetch = GitCommand::new(repo_dir).args(&["fetch", "--no-tags", bundle_path]);
+        for refspec in &refspecs {
+            fetch = fetch.arg(refspec);
+        }
+        fetch.run().await?;
+    }
+
+    GitCommand::new(repo_dir)
+        .args(&["checkout", "--quiet", "--detach", &repo.head_commit])
+        .run()
+        .await?;
+    for (branch, commit) in &repo.branches {
+        GitCommand::new(repo_dir)
+            .args(&["branch", "--force", branch, commit])
+            .run()
+            .await?;
+        if let Some(upstream) = repo.upstreams.get(branch) {
+            // the upstream might not exist anymore, which is fine
+            GitCommand::new(repo_dir)
+                .args(&["branch", "--set-upstream-to", upstream, branch])
+                .output()
+                .await?;
+        }
+    }
+    if let Some(branch) = &repo.head_branch {
+        GitCommand::new(repo_dir)
+            .args(&["checkout", "--quiet", branch])
+            .run()
+            .await?;
+    }
+
+    let bundle_refs = GitCommand::new(repo_dir)
+        .args(&["for-each-ref", "--format=%(refname)", "refs/nut/bundle"])
+        .output_string()
+        .await?;
+    for bundle_ref in bundle_refs.lines() {
+        GitCommand::new(repo_dir)
+            .args(&["update-ref", "-d", bundle_ref])
+            .run()
+            .await?;
+    }
+
+    if repo.has_staged_patch {
+        let patch = staging_dir.join("staged.patch");
+        GitCommand::new(repo_dir)
+            .args(&[
+                "apply",
+                "--index",
+                patch.to_str().ok_or(NutError::InvalidUtf8)?,
+            ])
+            .run()
+            .await?;
+    }
+    if repo.has_unstaged_patch {
+        let patch = staging_dir.join("unstaged.patch");
+        GitCommand::new(repo_dir)
+            .args(&["apply", patch.to_str().ok_or(NutError::InvalidUtf8)?])
+            .run()
+            .await?;
+    }
+    for file in &repo.untracked_files {
+        copy_file(
+            &staging_dir.join("untracked").join(file),
+            &repo_dir.join(file),
+        )?;
+    }
+
+    Ok(())
+}
+
+fn write_archive(staging_dir: &Path, output: &Path) -> std::io::Result<()> {
+    let file = std::fs::File::create(output)?;
+    let mut builder = tar::Builder::new(zstd::Encoder::new(file, 0)?);
+    builder.follow_symlinks(false);
+    builder.append_dir_all(".", staging_dir)?;
+    builder.into_inner()?.finish()?;
+    Ok(())
+}
+
+fn read_archive(bundle: &Path, staging_dir: &Path) -> std::io::Result<()> {
+    let file = std::fs::File::open(bundle)?;
+    tar::Archive::new(zstd::Decoder::new(file)?).unpack(staging_dir)
+}
+
+fn create_staging_dir(purpose: &str) -> Result<PathBuf> {
+    let path = std::env::temp_dir().join(format!("nut-{purpose}-{}", Ulid::new()));
+    create_dir(&path)?;
+    Ok(path)
+}
+
+fn create_dir(path: &Path) -> Result<()> {
+    std::fs::create_dir_all(path).map_err(|e| NutError::CreateDirectoryFailed {
+        path: path.to_path_buf(),
+        source: e,
+    })
+}
+
+fn write_file(path: &Path, contents: &[u8]) -> Result<()> {
+    std::fs::write(path, contents).map_err(|e| NutError::WriteFileFailed {
+        path: path.to_path_buf(),
+        source: e,
+    })
+}
+
+/// Copy a file, or a symbolic link as a link
+fn copy_file(from: &Path, to: &Path) -> Result<()> {
+    if let Some(parent) = to.parent() {
+        create_dir(parent)?;
+    }
+    let copy = || {
+        if std::fs::symlink_metadata(from)?.file_type().is_symlink() {
+            std::os::unix::fs::symlink(std::fs::read_link(from)?, to)
+        } else {
+            std::fs::copy(from, to).map(|_| ())
+        }
+    };
+    copy().map_err(|e| NutError::WriteFileFailed {
+        path: to.to_path_buf(),
+        source: e,
+    })
+}
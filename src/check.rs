@@ -0,0 +1,268 @@
+use std::fmt::Write as _;
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use std::time::{Duration, Instant};
+
+use crate::apply;
+use crate::config::NutConfig;
+use crate::ecosystem::Ecosystem;
+use crate::error::{NutError, Result};
+use crate::git;
+use crate::output::column_width;
+use crate::workspace::Workspace;
+
+/// Number of log lines included with failures in JUnit reports
+const JUNIT_LOG_LINES: usize = 100;
+
+pub struct CheckOptions {
+    /// Write a JUnit XML report to this file
+    pub junit: Option<PathBuf>,
+}
+
+enum CheckOutcome {
+    Passed,
+    Failed(String),
+    Skipped(String),
+}
+
+struct CheckResult {
+    repo: PathBuf,
+    ecosystem: Option<Ecosystem>,
+    outcome: CheckOutcome,
+    duration: Duration,
+    log: PathBuf,
+}
+
+/// Build and test every repository with the command for its ecosystem, one after another
+pub async fn check(workspace: &Workspace, options: &CheckOptions) -> Result<()> {
+    let config = NutConfig::load()?;
+    let log_dir = workspace.path.join(".nut").join("check");
+    if log_dir.exists() {
+        std::fs::remove_dir_all(&log_dir).map_err(|e| NutError::WriteFileFailed {
+            path: log_dir.clone(),
+            source: e,
+        })?;
+    }
+
+    let mut results = Vec::new();
+    for repo in git::find_repositories(&workspace.path)? {
+        let repo_dir = workspace.path.join(&repo);
+        let mut log = log_dir.join(&repo);
+        log.as_mut_os_string().push(".log");
+
+        let Some(ecosystem) = Ecosystem::detect(&repo_dir) else {
+            println!("{}: skipped, no known ecosystem", repo.display());
+            results.push(CheckResult {
+                repo,
+                ecosystem: None,
+                outcome: CheckOutcome::Skipped("no known ecosystem".to_string()),
+                duration: Duration::ZERO,
+                log,
+            });
+            continue;
+        };
+
+        let command = config
+            .check_commands
+            .get(ecosystem.name())
+            .map(|c| c.as_str())
+            .unwrap_or(ecosystem.default_check_command());
+        print!("{} ({ecosystem}): {command} ... ", repo.display());
+        std::io::Write::flush(&mut std::io::stdout()).ok();
+
+        let started = Instant::now();
+        let outcome = match run_check(&repo_dir, command, &log).await? {
+            None => CheckOutcome::Passed,
+            Some(message) => CheckOutcome::Failed(message),
+        };
+        let duration = started.elapsed();
+        match &outcome {
+            CheckOutcome::Failed(message) => println!("failed ({message})"),
+            _ => println!("passed"),
+        }
+        results.push(CheckResult {
+            repo,
+            ecosystem: Some(ecosystem),
+            outcome,
+            duration,
+            log,
+        });
+    }
+
+    println!();
+    print_matrix(&results);
+
+    if let Some(path) = &options.junit {
+        let xml = junit_report(workspace, &results);
+        std::fs::write(path, xml).map_err(|e| NutError::WriteFileFailed {
+            path: path.clone(),
+            source: e,
+        })?;
+    }
+
+    let failed = results
+        .iter()
+        .filter(|r| matches!(r.outcome, CheckOutcome::Failed(_)))
+        .count();
+    if failed > 0 {
+        return Err(NutError::CheckFailed {
+            failed,
+            log_dir: log_dir.display().to_string(),
+        });
+    }
+    Ok(())
+}
+
+/// Run the check command with its output written to `log`, returning the reason if it failed
+async fn run_check(repo_dir: &Path, command: &str, log: &Path) -> Result<Option<String>> {
+    let write_failed = |e| NutError::WriteFileFailed {
+        path: log.to_path_buf(),
+        source: e,
+    };
+    if let Some(parent) = log.parent() {
+        std::fs::create_dir_all(parent).map_err(|e| NutError::CreateDirectoryFailed {
+            path: parent.to_path_buf(),
+            source: e,
+        })?;
+    }
+    let stdout = std::fs::File::create(log).map_err(write_failed)?;
+    let stderr = stdout.try_clone().map_err(write_failed)?;
+
+    let shell_command = apply::shell_command(command)?;
+    let status = tokio::process::Command::new(&shell_command[0])
+        .args(&shell_command[1..])
+        .current_dir(repo_dir)
+        .stdin(Stdio::null())
+        .stdout(stdout)
+        .stderr(stderr)
+        .status()
+        .await;
+    Ok(match status {
+        Ok(status) if status.success() => None,
+        Ok(status) => Some(apply::describe_exit_status(&status)),
+        Err(e) => Some(e.to_string()),
+    })
+}
+
+fn print_matrix(results: &[CheckResult]) {
+    let repo_width = column_width(
+        "REPOSITORY",
+        results.iter().map(|r| r.repo.display().to_string()),
+    );
+    let ecosystem_width = column_width(
+        "ECOSYSTEM",
+        results
+            .iter()
+            .map(|r| ecosystem_name(r.ecosystem).to_string()),
+    );
+    println!(
+        "{:repo_width$}  {:ecosystem_width$}  {:8}  RESULT",
+        "REPOSITORY", "ECOSYSTEM", "DURATION"
+    );
+    for result in results {
+        let outcome = match &result.outcome {
+            CheckOutcome::Passed => "passed".to_string(),
+            CheckOutcome::Failed(_) => format!("FAILED, see {}", result.log.display()),
+            CheckOutcome::Skipped(reason) => format!("skipped, {reason}"),
+        };
+        println!(
+            "{:repo_width$}  {:ecosystem_width$}  {:>7.1}s  {}",
+            result.repo.display().to_string(),
+            ecosystem_name(result.ecosystem),
+            result.duration.as_secs_f64(),
+            outcome
+        );
+    }
+}
+
+fn ecosystem_name(ecosystem: Option<Ecosystem>) -> &'static str {
+    ecosystem.map(Ecosystem::name).unwrap_or("-")
+}
+
+/// JUnit XML with one test case per repository, as understood by most CI systems
+fn junit_report(workspace: &Workspace, results: &[CheckResult]) -> String {
+    let count = |f: fn(&CheckOutcome) -> bool| results.iter().filter(|r| f(&r.outcome)).count();
+    let failures = count(|o| matches!(o, CheckOutcome::Failed(_)));
+    let skipped = count(|o| matches!(o, CheckOutcome::Skipped(_)));
+    let time: f64 = results.iter().map(|r| r.duration.as_secs_f64()).sum();
+
+    let mut xml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    let _ = writeln!(
+        xml,
+        "<testsuites name=\"nut check\" tests=\"{}\" failures=\"{failures}\" skipped=\"{skipped}\" time=\"{time:.3}\">",
+        results.len()
+    );
+    let _ = writeln!(
+        xml,
+        "  <testsuite name=\"{}\" tests=\"{}\" failures=\"{failures}\" skipped=\"{skipped}\" time=\"{time:.3}\">",
+        xml_escape(&workspace.id.to_string()),
+        results.len()
+    );
+    for result in results {
+        let _ = write!(
+            xml,
+            "    <testcase name=\"{}\" classname=\"{}\" time=\"{:.3}\"",
+            xml_escape(&result.repo.to_string_lossy()),
+            ecosystem_name(result.ecosystem),
+            result.duration.as_secs_f64()
+        );
+        match &result.outcome {
+            CheckOutcome::Passed => xml.push_str("/>\n"),
+            CheckOutcome::Skipped(reason) => {
+                let _ = writeln!(
+                    xml,
+                    ">\n      <skipped message=\"{}\"/>\n    </testcase>",
+                    xml_escape(reason)
+                );
+            }
+            CheckOutcome::Failed(message) => {
+                let _ = writeln!(
+                    xml,
+                    ">\n      <failure message=\"{}\">{}</failure>\n    </testcase>",
+                    xml_escape(message),
+                    xml_escape(&log_tail(&result.log))
+                );
+            }
+        }
+    }
+    xml.push_str("  </testsuite>\n</testsuites>\n");
+    xml
+}
+
+fn log_tail(log: &Path) -> String {
+    let content = std::fs::read(log).unwrap_or_default();
+    let content = String::from_utf8_lossy(&content);
+    let lines: Vec<_> = content.lines().collect();
+    lines[lines.len().saturating_sub(JUNIT_LOG_LINES)..].join("\n")
+}
+
+/// Escape text for XML, dropping control characters XML 1.0 does not allow
+fn xml_escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            '\t' | '\n' | '\r' => escaped.push(c),
+            c if c.is_control() => {}
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_escapes_xml() {
+        assert_eq!(
+            xml_escape("a < b && \"c\"\x1b[31m\n"),
+            "a &lt; b &amp;&amp; &quot;c&quot;[31m\n"
+        );
+    }
+}
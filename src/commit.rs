@@ -0,0 +1,141 @@
+use std::path::{Path, PathBuf};
+
+use crate::error::{NutError, Result};
+use crate::git::{self, GitCommand};
+use crate::oplog;
+use crate::workspace::Workspace;
+
+pub struct CommitOptions {
+    /// Commit message, may contain `{repo}` and `{branch}`
+    pub template: String,
+    /// Stage all changes, including untracked files
+    pub all: bool,
+    /// Stage changes matching these pathspecs
+    pub pathspec: Vec<String>,
+}
+
+enum Outcome {
+    Committed(String),
+    Clean,
+    Failed(String),
+}
+
+/// Commit in every repository with staged changes, using the same message
+pub async fn commit(workspace: &Workspace, options: &CommitOptions) -> Result<()> {
+    let mut recorder = oplog::Recorder::start(workspace, "commit").await?;
+
+    let (mut committed, mut clean, mut failed) = (0, 0, 0);
+    for repo in git::find_repositories(&workspace.path)? {
+        let repo_dir = workspace.path.join(&repo);
+        match commit_repo(&repo_dir, &repo, options).await {
+            Outcome::Committed(commit) => {
+                println!("{}: committed {commit}", repo.display());
+                recorder.set_result(&repo, format!("committed {commit}"));
+                committed += 1;
+            }
+            Outcome::Clean => clean += 1,
+            Outcome::Failed(reason) => {
+                println!("{}: failed", repo.display());
+                for line in reason.lines() {
+                    println!("    {line}");
+                }
+                recorder.set_result(&repo, "failed");
+                failed += 1;
+            }
+        }
+    }
+    recorder.finish().await?;
+
+    println!();
+    println!("Committed in {committed} repositories, {failed} failed, {clean} without changes");
+    if failed > 0 {
+        return Err(NutError::CommitFailed { failed });
+    }
+    Ok(())
+}
+
+async fn commit_repo(repo_dir: &Path, repo: &Path, options: &CommitOptions) -> Outcome {
+    if options.all || !options.pathspec.is_empty() {
+        let mut add = GitCommand::new(repo_dir).args(&["add", "--all", "--"]);
+        for pathspec in &options.pathspec {
+            add = add.arg(pathspec);
+        }
+        if let Err(e) = add.checked_output().await {
+            return Outcome::Failed(e.to_string());
+        }
+    }
+
+    // --quiet exits with 1 if there are staged changes
+    let staged = GitCommand::new(repo_dir)
+        .args(&["diff", "--cached", "--quiet"])
+        .output()
+        .await;
+    match staged {
+        Ok(output) if output.status.success() => return Outcome::Clean,
+        Ok(_) => {}
+        Err(e) => return Outcome::Failed(e.to_string()),
+    }
+
+    let branch = git::head_state(repo_dir).await.branch.unwrap_or_default();
+    let message = render(&options.template, repo, &branch);
+    let output = GitCommand::new(repo_dir)
+        .args(&["commit", "--quiet", "-m", &message])
+        .output()
+        .await;
+    match output {
+        Ok(output) if output.status.success() => {
+            let commit = GitCommand::new(repo_dir)
+                .args(&["rev-parse", "--short", "HEAD"])
+                .output_string()
+                .await
+                .unwrap_or_default();
+            Outcome::Committed(commit)
+        }
+        // hooks and git itself might write errors to either stream
+        Ok(output) => Outcome::Failed(format!(
+            "{}{}",
+            String::from_utf8_lossy(&output.stdout),
+            String::from_utf8_lossy(&output.stderr)
+        )),
+        Err(e) => Outcome::Failed(e.to_string()),
+    }
+}
+
+/// Read the message template from `--message` or `--message-file`
+pub async fn read_template(message: &Option<String>, file: &Option<PathBuf>) -> Result<String> {
+    match (message, file) {
+        (Some(message), _) => Ok(message.clone()),
+        (None, Some(path)) => {
+            tokio::fs::read_to_string(path)
+                .await
+                .map_err(|e| NutError::ReadFileFailed {
+                    path: path.clone(),
+                    source: e,
+                })
+        }
+        (None, None) => Err(NutError::CommitMessageMissing),
+    }
+}
+
+fn render(template: &str, repo: &Path, branch: &str) -> String {
+    template
+        .replace("{repo}", &repo.to_string_lossy())
+        .replace("{branch}", branch)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render() {
+        assert_eq!(
+            render(
+                "chore({repo}): bump on {branch}",
+                Path::new("org/repo"),
+                "main"
+            ),
+            "chore(org/repo): bump on main"
+        );
+    }
+}
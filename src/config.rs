@@ -1,10 +1,26 @@
 use crate::error::Result;
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use std::path::PathBuf;
 
 #[derive(Debug, Serialize, Deserialize, Default)]
 pub struct NutConfig {
     pub workspace_dir: Option<PathBuf>,
+    /// Directory with named scripts for 'nut apply --recipe'
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub recipes_dir: Option<PathBuf>,
+    /// Shell for 'nut apply --shell', defaults to $SHELL
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub shell: Option<PathBuf>,
+    /// Commands for 'nut check' by ecosystem name, replacing the defaults
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub check_commands: BTreeMap<String, String>,
+    /// Shell commands by hook name, run before the scripts in `.nut/hooks/`
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub hooks: BTreeMap<String, String>,
+    /// Git config values set in every cloned repository, unless the workspace overrides them
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub git_config: BTreeMap<String, String>,
 }
 
 impl NutConfig {
@@ -0,0 +1,467 @@
+use std::cmp::Ordering;
+use std::collections::BTreeMap;
+
+use serde::Serialize;
+
+use crate::ecosystem::Ecosystem;
+use crate::error::{NutError, Result};
+use crate::git;
+use crate::output::{column_width, print_json};
+use crate::workspace::Workspace;
+
+/// Lockfiles and manifests with pinned versions, in the repository root
+const SOURCES: [(&str, Ecosystem); 4] = [
+    ("Cargo.lock", Ecosystem::Rust),
+    ("package-lock.json", Ecosystem::Node),
+    ("go.mod", Ecosystem::Go),
+    ("requirements.txt", Ecosystem::Python),
+];
+
+#[derive(clap::ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum DepsFormat {
+    #[default]
+    Text,
+    Json,
+    Csv,
+}
+
+/// A dependency version used by a repository
+#[derive(Serialize, Clone, PartialEq, Eq)]
+pub struct Dependency {
+    pub name: String,
+    pub ecosystem: Ecosystem,
+    pub version: String,
+    pub repository: String,
+    pub file: &'static str,
+}
+
+/// Versions of a dependency in ascending order, with the repositories using them
+type Versions<'a> = Vec<(&'a str, Vec<&'a str>)>;
+
+/// Version requirement like `<4.17.21`, compared segment by segment
+struct Requirement {
+    op: &'static str,
+    version: String,
+}
+
+impl Requirement {
+    fn parse(requirement: &str) -> Option<Self> {
+        ["<=", ">=", "==", "<", ">", "="]
+            .into_iter()
+            .find_map(|op| {
+                let version = requirement.strip_prefix(op)?.trim();
+                (!version.is_empty()).then(|| Self {
+                    op,
+                    version: version.to_string(),
+                })
+            })
+    }
+
+    fn matches(&self, version: &str) -> bool {
+        let ordering = compare_versions(version, &self.version);
+        match self.op {
+            "<" => ordering == Ordering::Less,
+            "<=" => ordering != Ordering::Greater,
+            ">" => ordering == Ordering::Greater,
+            ">=" => ordering != Ordering::Less,
+            _ => ordering == Ordering::Equal,
+        }
+    }
+}
+
+/// Print which repositories use which versions of every dependency
+pub async fn inventory(
+    workspace: &Workspace,
+    ecosystem: Option<Ecosystem>,
+    format: DepsFormat,
+) -> Result<()> {
+    let dependencies: Vec<_> = collect(workspace)?
+        .into_iter()
+        .filter(|d| ecosystem.is_none_or(|e| d.ecosystem == e))
+        .collect();
+
+    match format {
+        DepsFormat::Json => print_json(&dependencies),
+        DepsFormat::Csv => {
+            print_csv(&dependencies);
+            Ok(())
+        }
+        DepsFormat::Text => {
+            let mut grouped: BTreeMap<(&str, Ecosystem), Versions> = BTreeMap::new();
+            for dependency in &dependencies {
+                let versions = grouped
+                    .entry((&dependency.name, dependency.ecosystem))
+                    .or_default();
+                match versions.last_mut() {
+                    Some((version, repos)) if *version == dependency.version => {
+                        repos.push(&dependency.repository)
+                    }
+                    _ => versions.push((&dependency.version, vec![&dependency.repository])),
+                }
+            }
+            for ((name, ecosystem), versions) in grouped {
+                println!("{name} ({ecosystem})");
+                let width = column_width("", versions.iter().map(|(v, _)| v.to_string()));
+                for (version, repos) in versions {
+                    println!("  {version:width$}  {}", repos.join(", "));
+                }
+            }
+            Ok(())
+        }
+    }
+}
+
+/// Print the repositories using a dependency, optionally limited by a version requirement
+/// like `lodash<4.17.21`
+pub async fn why(
+    workspace: &Workspace,
+    query: &str,
+    ecosystem: Option<Ecosystem>,
+    format: DepsFormat,
+) -> Result<()> {
+    let (name, requirement) = match query.find(['<', '>', '=']) {
+        Some(index) => {
+            let (name, requirement) = query.split_at(index);
+            let requirement = Requirement::parse(requirement).ok_or_else(|| {
+                NutError::InvalidDependencyQuery {
+                    value: query.to_string(),
+                }
+            })?;
+            (name.trim(), Some(requirement))
+        }
+        None => (query.trim(), None),
+    };
+
+    let dependencies: Vec<_> = collect(workspace)?
+        .into_iter()
+        .filter(|d| ecosystem.is_none_or(|e| d.ecosystem == e))
+        .filter(|d| d.name == normalize_name(d.ecosystem, name))
+        .filter(|d| requirement.as_ref().is_none_or(|r| r.matches(&d.version)))
+        .collect();
+
+    match format {
+        DepsFormat::Json => print_json(&dependencies),
+        DepsFormat::Csv => {
+            print_csv(&dependencies);
+            Ok(())
+        }
+        DepsFormat::Text => {
+            if dependencies.is_empty() {
+                println!("No repository uses {query}");
+                return Ok(());
+            }
+            let repo_width = column_width(
+                "REPOSITORY",
+                dependencies.iter().map(|d| d.repository.clone()),
+            );
+            let version_width =
+                column_width("VERSION", dependencies.iter().map(|d| d.version.clone()));
+            println!(
+                "{:repo_width$}  {:version_width$}  FILE",
+                "REPOSITORY", "VERSION"
+            );
+            for dependency in &dependencies {
+                println!(
+                    "{:repo_width$}  {:version_width$}  {}",
+                    dependency.repository, dependency.version, dependency.file
+                );
+            }
+            Ok(())
+        }
+    }
+}
+
+/// Dependencies of all repositories, sorted by name, version and repository
+fn collect(workspace: &Workspace) -> Result<Vec<Dependency>> {
+    let mut dependencies = Vec::new();
+    for repo in git::find_repositories(&workspace.path)? {
+        let repo_dir = workspace.path.join(&repo);
+        for (file, ecosystem) in SOURCES {
+            let path = repo_dir.join(file);
+            let Ok(content) = std::fs::read_to_string(&path) else {
+                continue;
+            };
+            let parsed = match ecosystem {
+                Ecosystem::Rust => parse_cargo_lock(&content),
+                Ecosystem::Node => parse_package_lock(&content),
+                Ecosystem::Go => Ok(parse_go_mod(&content)),
+                _ => Ok(parse_requirements(&content)),
+            };
+            match parsed {
+                Ok(parsed) => {
+                    dependencies.extend(parsed.into_iter().map(|(name, version)| Dependency {
+                        name,
+                        ecosystem,
+                        version,
+                        repository: repo.to_string_lossy().to_string(),
+                        file,
+                    }))
+                }
+                Err(reason) => eprintln!("Skipping {}: {reason}", path.display()),
+            }
+        }
+    }
+    dependencies.sort_by(|a, b| {
+        (&a.name, a.ecosystem)
+            .cmp(&(&b.name, b.ecosystem))
+            .then_with(|| compare_versions(&a.version, &b.version))
+            .then_with(|| a.repository.cmp(&b.repository))
+    });
+    dependencies.dedup();
+    Ok(dependencies)
+}
+
+fn print_csv(dependencies: &[Dependency]) {
+    println!("ecosystem,name,version,repository,file");
+    for d in dependencies {
+        println!(
+            "{},{},{},{},{}",
+            d.ecosystem,
+            csv_field(&d.name),
+            csv_field(&d.version),
+            csv_field(&d.repository),
+            d.file
+        );
+    }
+}
+
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+/// Packages from the registry or git, skipping the crates of the repository itself
+fn parse_cargo_lock(content: &str) -> std::result::Result<Vec<(String, String)>, String> {
+    let document: toml_edit::DocumentMut = content.parse().map_err(|e| format!("{e}"))?;
+    let Some(packages) = document.get("package").and_then(|p| p.as_array_of_tables()) else {
+        return Ok(Vec::new());
+    };
+    Ok(packages
+        .iter()
+        .filter(|p| p.contains_key("source"))
+        .filter_map(|p| {
+            let name = p.get("name")?.as_str()?;
+            let version = p.get("version")?.as_str()?;
+            Some((name.to_string(), version.to_string()))
+        })
+        .collect())
+}
+
+/// Supports the `packages` map of lockfile version 2 and 3, and the nested
+/// `dependencies` of version 1
+fn parse_package_lock(content: &str) -> std::result::Result<Vec<(String, String)>, String> {
+    let lock: serde_json::Value = serde_json::from_str(content).map_err(|e| format!("{e}"))?;
+    let mut dependencies = Vec::new();
+    if let Some(packages) = lock.get("packages").and_then(|p| p.as_object()) {
+        for (path, package) in packages {
+            // the root package has an empty path, linked workspace packages have no node_modules
+            let Some((_, name)) = path.rsplit_once("node_modules/") else {
+                continue;
+            };
+            if package.get("link").and_then(|l| l.as_bool()) == Some(true) {
+                continue;
+            }
+            if let Some(version) = package.get("version").and_then(|v| v.as_str()) {
+                dependencies.push((name.to_string(), version.to_string()));
+            }
+        }
+    } else if let Some(tree) = lock.get("dependencies") {
+        collect_package_lock_v1(tree, &mut dependencies);
+    }
+    Ok(dependencies)
+}
+
+fn collect_package_lock_v1(tree: &serde_json::Value, dependencies: &mut Vec<(String, String)>) {
+    let Some(tree) = tree.as_object() else {
+        return;
+    };
+    for (name, package) in tree {
+        if let Some(version) = package.get("version").and_then(|v| v.as_str()) {
+            dependencies.push((name.clone(), version.to_string()));
+        }
+        if let Some(nested) = package.get("dependencies") {
+            collect_package_lock_v1(nested, dependencies);
+        }
+    }
+}
+
+/// Modules from `require` directives, single or in a block, which list the versions
+/// the build uses. go.sum also has checksums of versions that are not used anymore
+fn parse_go_mod(content: &str) -> Vec<(String, String)> {
+    let mut in_block = false;
+    content
+        .lines()
+        .filter_map(|line| {
+            let line = line.split("//").next().unwrap_or_default().trim();
+            let requirement = if in_block {
+                if line == ")" {
+                    in_block = false;
+                    return None;
+                }
+                line
+            } else {
+                let rest = line.strip_prefix("require")?;
+                if rest.trim() == "(" {
+                    in_block = true;
+                    return None;
+                }
+                rest
+            };
+            let mut fields = requirement.split_whitespace();
+            let module = fields.next()?;
+            let version = fields.next()?;
+            Some((module.to_string(), version.to_string()))
+        })
+        .collect()
+}
+
+/// Only pinned requirements like `requests==2.31.0` have a version
+fn parse_requirements(content: &str) -> Vec<(String, String)> {
+    content
+        .lines()
+        .map(|line| line.split('#').next().unwrap_or_default().trim())
+        .filter_map(|line| {
+            let line = line.split(';').next()?.trim();
+            let (name, version) = line.split_once("==")?;
+            // extras like `requests[socks]` belong to the same package
+            let name = name.split('[').next()?.trim();
+            Some((
+                normalize_name(Ecosystem::Python, name),
+                version.trim().to_string(),
+            ))
+        })
+        .collect()
+}
+
+/// Python package names are case-insensitive and treat runs of `-`, `_` and `.` alike,
+/// e.g. `Typing_Extensions` is `typing-extensions`
+fn normalize_name(ecosystem: Ecosystem, name: &str) -> String {
+    if ecosystem != Ecosystem::Python {
+        return name.to_string();
+    }
+    let mut normalized = String::new();
+    for c in name.chars() {
+        if matches!(c, '-' | '_' | '.') {
+            if !normalized.ends_with('-') {
+                normalized.push('-');
+            }
+        } else {
+            normalized.push(c.to_ascii_lowercase());
+        }
+    }
+    normalized
+}
+
+/// Compare versions from any ecosystem segment by segment, numerically where possible,
+/// e.g. `v1.10.0` > `1.9.2` and `4.17.21` > `4.17.21-beta.1`
+pub fn compare_versions(a: &str, b: &str) -> Ordering {
+    let split = |v: &str| {
+        let v = v.trim_start_matches('v');
+        let (release, pre_release) = match v.split_once(['-', '+']) {
+            Some((release, pre_release)) => (release.to_string(), Some(pre_release.to_string())),
+            None => (v.to_string(), None),
+        };
+        (release, pre_release)
+    };
+    let (release_a, pre_a) = split(a);
+    let (release_b, pre_b) = split(b);
+
+    let mut segments_a = release_a.split('.');
+    let mut segments_b = release_b.split('.');
+    loop {
+        let ordering = match (segments_a.next(), segments_b.next()) {
+            (None, None) => break,
+            (a, b) => compare_segments(a.unwrap_or("0"), b.unwrap_or("0")),
+        };
+        if ordering != Ordering::Equal {
+            return ordering;
+        }
+    }
+
+    // a pre-release comes before the release
+    match (pre_a, pre_b) {
+        (None, None) => Ordering::Equal,
+        (Some(_), None) => Ordering::Less,
+        (None, Some(_)) => Ordering::Greater,
+        (Some(a), Some(b)) => a.split('.').cmp(b.split('.')),
+    }
+}
+
+fn compare_segments(a: &str, b: &str) -> Ordering {
+    match (a.parse::<u64>(), b.parse::<u64>()) {
+        (Ok(a), Ok(b)) => a.cmp(&b),
+        _ => a.cmp(b),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_compares_versions() {
+        assert_eq!(compare_versions("4.17.20", "4.17.21"), Ordering::Less);
+        assert_eq!(compare_versions("v1.10.0", "1.9.2"), Ordering::Greater);
+        assert_eq!(compare_versions("1.0", "1.0.0"), Ordering::Equal);
+        assert_eq!(
+            compare_versions("4.17.21-beta.1", "4.17.21"),
+            Ordering::Less
+        );
+        assert!(Requirement::parse("<4.17.21").unwrap().matches("4.17.4"));
+        assert!(!Requirement::parse(">=2").unwrap().matches("1.9"));
+    }
+
+    #[test]
+    fn test_parses_lockfiles() {
+        let cargo_lock = r#"
+[[package]]
+name = "app"
+version = "0.1.0"
+
+[[package]]
+name = "serde"
+version = "1.0.200"
+source = "registry+https://github.com/rust-lang/crates.io-index"
+"#;
+        assert_eq!(
+            parse_cargo_lock(cargo_lock).unwrap(),
+            [("serde".to_string(), "1.0.200".to_string())]
+        );
+
+        let package_lock = r#"{"packages": {
+            "": {"name": "app"},
+            "node_modules/lodash": {"version": "4.17.20"},
+            "node_modules/a/node_modules/@scope/b": {"version": "1.0.0"}
+        }}"#;
+        assert_eq!(
+            parse_package_lock(package_lock).unwrap(),
+            [
+                ("lodash".to_string(), "4.17.20".to_string()),
+                ("@scope/b".to_string(), "1.0.0".to_string())
+            ]
+        );
+
+        let go_mod = "module example.com/app\n\ngo 1.21\n\nrequire github.com/pkg/errors v0.9.1\n\n\
+                      require (\n\tgolang.org/x/net v0.17.0\n\tgolang.org/x/text v0.13.0 // indirect\n)\n";
+        assert_eq!(
+            parse_go_mod(go_mod),
+            [
+                ("github.com/pkg/errors".to_string(), "v0.9.1".to_string()),
+                ("golang.org/x/net".to_string(), "v0.17.0".to_string()),
+                ("golang.org/x/text".to_string(), "v0.13.0".to_string())
+            ]
+        );
+
+        let requirements = "# comment\nRequests[socks]==2.31.0 ; python_version > '3'\nflask>=2\nTyping_Extensions==4.8.0\n";
+        assert_eq!(
+            parse_requirements(requirements),
+            [
+                ("requests".to_string(), "2.31.0".to_string()),
+                ("typing-extensions".to_string(), "4.8.0".to_string())
+            ]
+        );
+    }
+}
@@ -0,0 +1,201 @@
+use std::collections::BTreeMap;
+use std::io::{IsTerminal, Write};
+use std::path::{Path, PathBuf};
+
+use crate::error::{NutError, Result};
+use crate::git::{self, GitCommand};
+use crate::output;
+use crate::workspace::Workspace;
+
+/// Value of `--against` that compares each repository with its remote default branch
+pub const DEFAULT_BRANCH: &str = "default-branch";
+
+pub struct DiffOptions {
+    /// Show staged instead of unstaged changes
+    pub staged: bool,
+    /// Only show a summary of changed files
+    pub stat: bool,
+    /// Compare with the merge base of this ref, or the remote default branch
+    pub against: Option<String>,
+    /// Show repositories with identical changes together
+    pub group: bool,
+    pub pager: bool,
+}
+
+/// Combined diff of all repositories in a workspace
+pub async fn diff(workspace: &Workspace, options: &DiffOptions) -> Result<()> {
+    let color = options.pager && std::io::stdout().is_terminal();
+    let repos = git::find_repositories(&workspace.path)?;
+
+    let mut output = Vec::new();
+    if options.group {
+        group_diffs(workspace, &repos, options, color, &mut output).await;
+    } else {
+        let futures = repos
+            .iter()
+            .map(|repo| repo_diff(&workspace.path, repo, options, color, true));
+        let diffs = futures_util::future::join_all(futures).await;
+        for (repo, diff) in repos.iter().zip(diffs) {
+            match diff {
+                Ok(diff) if diff.is_empty() => {}
+                Ok(diff) => {
+                    output.extend(format!("==> {} <==\n", repo.display()).as_bytes());
+                    output.extend(diff);
+                    output.push(b'\n');
+                }
+                Err(e) => eprintln!("{}: skipped, {e}", repo.display()),
+            }
+        }
+    }
+
+    if output.is_empty() {
+        println!("No changes");
+        return Ok(());
+    }
+    if options.pager {
+        output::page(&output, &workspace.path).await
+    } else {
+        std::io::stdout().write_all(&output).ok();
+        Ok(())
+    }
+}
+
+/// Print each distinct diff once, followed by the repositories it applies to.
+/// The largest groups come first, so outliers end up at the bottom.
+async fn group_diffs(
+    workspace: &Workspace,
+    repos: &[PathBuf],
+    options: &DiffOptions,
+    color: bool,
+    output: &mut Vec<u8>,
+) {
+    let patch_options = DiffOptions {
+        stat: false,
+        against: options.against.clone(),
+        ..*options
+    };
+    let futures = repos
+        .iter()
+        .map(|repo| repo_diff(&workspace.path, repo, &patch_options, false, false));
+    let diffs = futures_util::future::join_all(futures).await;
+
+    let mut groups: BTreeMap<String, Vec<&PathBuf>> = BTreeMap::new();
+    let mut unchanged = Vec::new();
+    for (repo, diff) in repos.iter().zip(diffs) {
+        match diff {
+            Ok(diff) if diff.is_empty() => unchanged.push(repo),
+            Ok(diff) => groups
+                .entry(normalize(&String::from_utf8_lossy(&diff)))
+                .or_default()
+                .push(repo),
+            Err(e) => eprintln!("{}: skipped, {e}", repo.display()),
+        }
+    }
+
+    let mut groups: Vec<Vec<&PathBuf>> = groups.into_values().collect();
+    groups.sort_by_key(|repos| std::cmp::Reverse(repos.len()));
+
+    for repos in groups {
+        if repos.len() == 1 {
+            output.extend(format!("==> {} <==\n", repos[0].display()).as_bytes());
+        } else {
+            output.extend(
+                format!("==> same changes in {} repositories <==\n", repos.len()).as_bytes(),
+            );
+            for repo in &repos {
+                output.extend(format!("{}\n", repo.display()).as_bytes());
+            }
+            output.push(b'\n');
+        }
+        // show the changes of the first repository, without repository specific prefixes
+        match repo_diff(&workspace.path, repos[0], options, color, false).await {
+            Ok(diff) => output.extend(diff),
+            Err(e) => output.extend(format!("{e}\n").as_bytes()),
+        }
+        output.push(b'\n');
+    }
+
+    if !unchanged.is_empty() && !output.is_empty() {
+        output
+            .extend(format!("==> no changes in {} repositories <==\n", unchanged.len()).as_bytes());
+        for repo in unchanged {
+            output.extend(format!("{}\n", repo.display()).as_bytes());
+        }
+    }
+}
+
+async fn repo_diff(
+    workspace_path: &Path,
+    repo: &Path,
+    options: &DiffOptions,
+    color: bool,
+    prefixed: bool,
+) -> Result<Vec<u8>> {
+    let repo_dir = workspace_path.join(repo);
+
+    let base = match options.against.as_deref() {
+        Some(DEFAULT_BRANCH) => {
+            let branch = git::remote_default_branch(&repo_dir).await.ok_or_else(|| {
+                NutError::DefaultBranchUnknown {
+                    repo: repo.display().to_string(),
+                }
+            })?;
+            Some(format!("origin/{branch}"))
+        }
+        Some(base) => Some(base.to_string()),
+        None => None,
+    };
+    // prefix paths with the repository, e.g. a/org/repo/README.md
+    let src_prefix = format!("--src-prefix=a/{}/", repo.display());
+    let dst_prefix = format!("--dst-prefix=b/{}/", repo.display());
+
+    let color = if color {
+        "--color=always"
+    } else {
+        "--color=never"
+    };
+
+    let mut command = GitCommand::new(&repo_dir).args(&["diff", color]);
+    if options.staged {
+        command = command.arg("--cached");
+    }
+    if options.stat {
+        command = command.arg("--stat");
+    }
+    if prefixed {
+        command = command.args(&[&src_prefix, &dst_prefix]);
+    }
+    if let Some(base) = &base {
+        command = command.args(&["--merge-base", base]);
+    }
+    command.checked_output().await
+}
+
+/// Drop the parts of a diff that differ between repositories even if the
+/// change is the same: blob hashes and hunk line numbers
+fn normalize(diff: &str) -> String {
+    diff.lines()
+        .filter(|l| !l.starts_with("index "))
+        .map(|l| match l.strip_prefix("@@ ") {
+            Some(hunk) => match hunk.split_once(" @@") {
+                Some((_, context)) => format!("@@{context}"),
+                None => l.to_string(),
+            },
+            None => l.to_string(),
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_normalize_ignores_hashes_and_line_numbers() {
+        let a = "diff --git a/f b/f\nindex 1111111..2222222 100644\n--- a/f\n+++ b/f\n@@ -1,3 +1,3 @@ fn main() {\n-old\n+new\n";
+        let b = "diff --git a/f b/f\nindex 3333333..4444444 100644\n--- a/f\n+++ b/f\n@@ -10,3 +10,3 @@ fn main() {\n-old\n+new\n";
+        assert_eq!(normalize(a), normalize(b));
+        assert_ne!(normalize(a), normalize(&a.replace("+new", "+other")));
+    }
+}
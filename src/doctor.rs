@@ -0,0 +1,60 @@
+use crate::error::{NutError, Result};
+use crate::git;
+use crate::gitconfig;
+use crate::workspace::Workspace;
+
+pub struct DoctorOptions {
+    /// Re-apply the git config overrides where they differ
+    pub fix: bool,
+}
+
+/// Check that every repository of the workspace uses the git config overrides
+pub async fn doctor(workspace: &Workspace, options: &DoctorOptions) -> Result<()> {
+    let overrides = gitconfig::overrides(workspace).await?;
+    if overrides.is_empty() {
+        println!("No git config overrides configured");
+        return Ok(());
+    }
+    println!("Git config overrides:");
+    for (key, value) in &overrides {
+        println!("  {key} = {value}");
+    }
+    println!();
+
+    let repos = git::find_repositories(&workspace.path)?;
+    let mut problems = 0;
+    for repo in &repos {
+        let repo_dir = workspace.path.join(repo);
+        let mismatches = gitconfig::mismatches(&repo_dir, &overrides).await?;
+        if mismatches.is_empty() {
+            continue;
+        }
+        for mismatch in &mismatches {
+            let actual = match mismatch.actual.as_slice() {
+                [] => "not set".to_string(),
+                actual => format!("'{}'", actual.join("', '")),
+            };
+            println!(
+                "{}: {} is {actual} instead of '{}'",
+                repo.display(),
+                mismatch.key,
+                mismatch.expected
+            );
+        }
+        if options.fix {
+            gitconfig::apply(&repo_dir, &overrides).await?;
+            println!("{}: fixed", repo.display());
+        } else {
+            problems += 1;
+        }
+    }
+
+    if problems > 0 {
+        return Err(NutError::DoctorFoundProblems { count: problems });
+    }
+    println!(
+        "All {} repositories use the git config overrides",
+        repos.len()
+    );
+    Ok(())
+}
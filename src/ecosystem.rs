@@ -0,0 +1,136 @@
+use std::fmt;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+/// Build systems recognized by the manifest files in the repository root
+#[derive(
+    clap::ValueEnum, Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord,
+)]
+#[serde(rename_all = "lowercase")]
+pub enum Ecosystem {
+    Rust,
+    Node,
+    Go,
+    Python,
+    Make,
+}
+
+impl Ecosystem {
+    /// In order of precedence, so a Rust project with a Makefile is checked with cargo
+    pub const ALL: [Ecosystem; 5] = [
+        Ecosystem::Rust,
+        Ecosystem::Node,
+        Ecosystem::Go,
+        Ecosystem::Python,
+        Ecosystem::Make,
+    ];
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|e| e.name() == name)
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Ecosystem::Rust => "rust",
+            Ecosystem::Node => "node",
+            Ecosystem::Go => "go",
+            Ecosystem::Python => "python",
+            Ecosystem::Make => "make",
+        }
+    }
+
+    /// Files in the repository root that identify the ecosystem
+    pub fn manifests(self) -> &'static [&'static str] {
+        match self {
+            Ecosystem::Rust => &["Cargo.toml"],
+            Ecosystem::Node => &["package.json"],
+            Ecosystem::Go => &["go.mod"],
+            Ecosystem::Python => &["pyproject.toml", "setup.py", "requirements.txt"],
+            Ecosystem::Make => &["Makefile", "GNUmakefile"],
+        }
+    }
+
+    /// Shell command building and testing a repository, unless configured otherwise
+    pub fn default_check_command(self) -> &'static str {
+        match self {
+            Ecosystem::Rust => "cargo test",
+            Ecosystem::Node => "npm test",
+            Ecosystem::Go => "go test ./...",
+            Ecosystem::Python => "python -m pytest",
+            Ecosystem::Make => "make test",
+        }
+    }
+
+    /// The primary ecosystem of a repository
+    pub fn detect(repo_dir: &Path) -> Option<Self> {
+        find_manifests(repo_dir)
+            .first()
+            .map(|(ecosystem, _)| *ecosystem)
+    }
+
+    /// All ecosystems of a repository, primary first
+    pub fn detect_all(repo_dir: &Path) -> Vec<Self> {
+        let mut ecosystems: Vec<Self> = find_manifests(repo_dir)
+            .into_iter()
+            .map(|(ecosystem, _)| ecosystem)
+            .collect();
+        ecosystems.dedup();
+        ecosystems
+    }
+}
+
+/// Manifest files in the repository root with their ecosystem, in order of precedence.
+/// Only looks at the working tree, so it works offline
+pub fn find_manifests(repo_dir: &Path) -> Vec<(Ecosystem, &'static str)> {
+    Ecosystem::ALL
+        .into_iter()
+        .flat_map(|ecosystem| {
+            ecosystem
+                .manifests()
+                .iter()
+                .map(move |manifest| (ecosystem, *manifest))
+        })
+        .filter(|(_, manifest)| repo_dir.join(manifest).is_file())
+        .collect()
+}
+
+impl fmt::Display for Ecosystem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detects_by_precedence() {
+        let dir = std::env::temp_dir().join(format!("nut-ecosystem-{}", ulid::Ulid::new()));
+        std::fs::create_dir_all(&dir).unwrap();
+        assert_eq!(Ecosystem::detect(&dir), None);
+
+        std::fs::write(dir.join("Makefile"), "").unwrap();
+        assert_eq!(Ecosystem::detect(&dir), Some(Ecosystem::Make));
+
+        std::fs::write(dir.join("Cargo.toml"), "").unwrap();
+        assert_eq!(Ecosystem::detect(&dir), Some(Ecosystem::Rust));
+
+        std::fs::write(dir.join("setup.py"), "").unwrap();
+        std::fs::write(dir.join("requirements.txt"), "").unwrap();
+        assert_eq!(
+            Ecosystem::detect_all(&dir),
+            [Ecosystem::Rust, Ecosystem::Python, Ecosystem::Make]
+        );
+        assert_eq!(
+            find_manifests(&dir)
+                .into_iter()
+                .map(|(_, m)| m)
+                .collect::<Vec<_>>(),
+            ["Cargo.toml", "setup.py", "requirements.txt", "Makefile"]
+        );
+
+        std::fs::remove_dir_all(&dir).ok();
+    }
+}
@@ -0,0 +1,863 @@
+use std::path::Path;
+
+use serde_json::Value;
+
+use crate::error::{NutError, Result};
+use crate::git;
+use crate::replace;
+use crate::workspace::Workspace;
+
+/// One segment of a path like `.dependencies."lodash.merge"` or `.bin[0].name`
+#[derive(Debug, Clone, PartialEq)]
+enum Segment {
+    Key(String),
+    Index(usize),
+}
+
+#[derive(Debug)]
+enum Edit {
+    Set(Vec<Segment>, Value),
+    Delete(Vec<Segment>),
+}
+
+#[derive(Clone, Copy)]
+enum Format {
+    Json,
+    Toml,
+    Yaml,
+}
+
+pub struct EditOptions {
+    /// File to edit, relative to each repository
+    pub file: String,
+    /// Assignments like `.engines.node=">=20"`
+    pub set: Vec<String>,
+    /// Paths to remove
+    pub delete: Vec<String>,
+    /// Show which repositories would change without writing files
+    pub dry_run: bool,
+}
+
+/// Change values in a JSON, YAML or TOML file in every repository that has it
+pub async fn edit(workspace: &Workspace, options: &EditOptions) -> Result<()> {
+    let format = detect_format(&options.file)?;
+    let mut edits = Vec::new();
+    for assignment in &options.set {
+        let (path, value) = split_assignment(assignment)
+            .ok_or_else(|| invalid_edit(assignment, "expected PATH=VALUE"))?;
+        let path = parse_path(path.trim()).map_err(|reason| invalid_edit(assignment, &reason))?;
+        // values that are not valid JSON are used as strings
+        let value = serde_json::from_str(value.trim())
+            .unwrap_or_else(|_| Value::String(value.trim().to_string()));
+        edits.push(Edit::Set(path, value));
+    }
+    for path in &options.delete {
+        let segments = parse_path(path).map_err(|reason| invalid_edit(path, &reason))?;
+        edits.push(Edit::Delete(segments));
+    }
+
+    let (mut changed, mut unchanged, mut missing, mut failed) = (0, 0, 0, 0);
+    for repo in git::find_repositories(&workspace.path)? {
+        let path = workspace.path.join(&repo).join(&options.file);
+        let Ok(content) = std::fs::read_to_string(&path) else {
+            missing += 1;
+            continue;
+        };
+
+        match edit_content(&content, format, &edits) {
+            Ok(updated) if updated == content => unchanged += 1,
+            Ok(updated) => {
+                if !options.dry_run {
+                    replace::write_atomic(&path, updated.as_bytes())?;
+                }
+                println!("{}: changed", repo.display());
+                changed += 1;
+            }
+            Err(reason) => {
+                println!("{}: failed, {reason}", repo.display());
+                failed += 1;
+            }
+        }
+    }
+
+    let verb = if options.dry_run {
+        "Would change"
+    } else {
+        "Changed"
+    };
+    println!(
+        "{verb} {} in {changed} repositories, {unchanged} unchanged, {failed} failed, {missing} without the file",
+        options.file
+    );
+    if failed > 0 {
+        return Err(NutError::EditFailed { failed });
+    }
+    Ok(())
+}
+
+fn invalid_edit(edit: &str, reason: &str) -> NutError {
+    NutError::InvalidEdit {
+        edit: edit.to_string(),
+        reason: reason.to_string(),
+    }
+}
+
+fn detect_format(file: &str) -> Result<Format> {
+    let extension = Path::new(file)
+        .extension()
+        .map(|e| e.to_string_lossy().to_lowercase());
+    match extension.as_deref() {
+        Some("json") => Ok(Format::Json),
+        Some("toml") => Ok(Format::Toml),
+        Some("yaml" | "yml") => Ok(Format::Yaml),
+        _ => Err(NutError::UnsupportedFileFormat {
+            file: file.to_string(),
+        }),
+    }
+}
+
+/// Split `PATH=VALUE` at the first `=` that is not part of a quoted key
+fn split_assignment(assignment: &str) -> Option<(&str, &str)> {
+    let mut quoted = false;
+    let (index, _) = assignment.char_indices().find(|&(_, c)| {
+        if c == '"' {
+            quoted = !quoted;
+        }
+        c == '=' && !quoted
+    })?;
+    Some((&assignment[..index], &assignment[index + 1..]))
+}
+
+/// Parse `.a.b[0]."c.d"` into segments
+fn parse_path(path: &str) -> std::result::Result<Vec<Segment>, String> {
+    let mut segments = Vec::new();
+    let mut chars = path.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '.' if chars.peek() == Some(&'"') => {
+                chars.next();
+                let mut key = String::new();
+                loop {
+                    match chars.next() {
+                        Some('"') => break,
+                        Some(c) => key.push(c),
+                        None => return Err("unterminated quote".to_string()),
+                    }
+                }
+                segments.push(Segment::Key(key));
+            }
+            '.' => {
+                let mut key = String::new();
+                while let Some(&c) = chars.peek() {
+                    if c == '.' || c == '[' {
+                        break;
+                    }
+                    key.push(c);
+                    chars.next();
+                }
+                if key.is_empty() {
+                    return Err("empty key".to_string());
+                }
+                segments.push(Segment::Key(key));
+            }
+            '[' => {
+                let index: String = chars.by_ref().take_while(|&c| c != ']').collect();
+                let index = index
+                    .parse()
+                    .map_err(|_| format!("invalid array index '{index}'"))?;
+                segments.push(Segment::Index(index));
+            }
+            _ => return Err("paths must start with '.', e.g. .engines.node".to_string()),
+        }
+    }
+    if segments.is_empty() {
+        return Err("empty path".to_string());
+    }
+    Ok(segments)
+}
+
+/// Files with Windows line endings keep them
+fn edit_content(
+    content: &str,
+    format: Format,
+    edits: &[Edit],
+) -> std::result::Result<String, String> {
+    if content.contains("\r\n") {
+        let updated = edit_content(&content.replace("\r\n", "\n"), format, edits)?;
+        return Ok(updated.replace('\n', "\r\n"));
+    }
+    match format {
+        Format::Json => edit_json(content, edits),
+        Format::Toml => edit_toml(content, edits),
+        Format::Yaml => {
+            let mut content = content.to_string();
+            for edit in edits {
+                content = match edit {
+                    Edit::Set(path, value) => yaml::set(&content, path, value)?,
+                    Edit::Delete(path) => yaml::delete(&content, path)?,
+                };
+            }
+            Ok(content)
+        }
+    }
+}
+
+fn edit_json(content: &str, edits: &[Edit]) -> std::result::Result<String, String> {
+    let mut document: Value = serde_json::from_str(content).map_err(|e| e.to_string())?;
+    for edit in edits {
+        match edit {
+            Edit::Set(path, value) => json_set(&mut document, path, value.clone())?,
+            Edit::Delete(path) => json_delete(&mut document, path),
+        }
+    }
+
+    // keep the indentation and trailing newline of the original file
+    let indent = content
+        .lines()
+        .skip(1)
+        .find(|l| !l.trim().is_empty())
+        .map(|l| &l[..l.len() - l.trim_start().len()])
+        .filter(|i| !i.is_empty())
+        .unwrap_or("  ");
+    let mut output = Vec::new();
+    let formatter = serde_json::ser::PrettyFormatter::with_indent(indent.as_bytes());
+    let mut serializer = serde_json::Serializer::with_formatter(&mut output, formatter);
+    serde::Serialize::serialize(&document, &mut serializer).map_err(|e| e.to_string())?;
+    let mut output = String::from_utf8_lossy(&output).to_string();
+    if content.ends_with('\n') {
+        output.push('\n');
+    }
+    Ok(output)
+}
+
+fn json_set(
+    document: &mut Value,
+    path: &[Segment],
+    value: Value,
+) -> std::result::Result<(), String> {
+    let Some((last, parents)) = path.split_last() else {
+        return Ok(());
+    };
+    let mut current = document;
+    for segment in parents {
+        current = match segment {
+            Segment::Key(key) => current
+                .as_object_mut()
+                .ok_or_else(|| format!("'{key}' is not inside an object"))?
+                .entry(key.clone())
+                .or_insert_with(|| Value::Object(Default::default())),
+            Segment::Index(index) => current
+                .as_array_mut()
+                .and_then(|a| a.get_mut(*index))
+                .ok_or_else(|| format!("array index {index} does not exist"))?,
+        };
+    }
+    match last {
+        Segment::Key(key) => {
+            current
+                .as_object_mut()
+                .ok_or_else(|| format!("'{key}' is not inside an object"))?
+                .insert(key.clone(), value);
+        }
+        Segment::Index(index) => {
+            let array = current
+                .as_array_mut()
+                .ok_or_else(|| format!("index {index} is not inside an array"))?;
+            match index.cmp(&array.len()) {
+                std::cmp::Ordering::Less => array[*index] = value,
+                std::cmp::Ordering::Equal => array.push(value),
+                std::cmp::Ordering::Greater => {
+                    return Err(format!("array index {index} does not exist"));
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Removing a path that does not exist is not an error
+fn json_delete(document: &mut Value, path: &[Segment]) {
+    let Some((last, parents)) = path.split_last() else {
+        return;
+    };
+    let mut current = document;
+    for segment in parents {
+        let next = match segment {
+            Segment::Key(key) => current.get_mut(key.as_str()),
+            Segment::Index(index) => current.get_mut(*index),
+        };
+        match next {
+            Some(next) => current = next,
+            None => return,
+        }
+    }
+    match last {
+        Segment::Key(key) => {
+            if let Some(object) = current.as_object_mut() {
+                object.shift_remove(key);
+            }
+        }
+        Segment::Index(index) => {
+            if let Some(array) = current.as_array_mut()
+                && *index < array.len()
+            {
+                array.remove(*index);
+            }
+        }
+    }
+}
+
+fn edit_toml(content: &str, edits: &[Edit]) -> std::result::Result<String, String> {
+    let mut document: toml_edit::DocumentMut = content.parse().map_err(|e| format!("{e}"))?;
+    for edit in edits {
+        match edit {
+            Edit::Set(path, value) => {
+                let value = toml_value(value)?;
+                let (last, parents) = split_key(path)?;
+                let table = toml_table(document.as_item_mut(), parents, true)?
+                    .ok_or_else(|| "path not found".to_string())?;
+                // keep the decoration, e.g. a trailing comment, of existing values
+                match table.get_mut(last).and_then(|i| i.as_value_mut()) {
+                    Some(existing) => {
+                        let decor = existing.decor().clone();
+                        *existing = value;
+                        *existing.decor_mut() = decor;
+                    }
+                    None => {
+                        table.insert(last, toml_edit::Item::Value(value));
+                    }
+                }
+            }
+            Edit::Delete(path) => {
+                let (last, parents) = split_key(path)?;
+                if let Some(table) = toml_table(document.as_item_mut(), parents, false)? {
+                    table.remove(last);
+                }
+            }
+        }
+    }
+    Ok(document.to_string())
+}
+
+/// The last segment must be a key, values in arrays can only be set in JSON files
+fn split_key(path: &[Segment]) -> std::result::Result<(&str, &[Segment]), String> {
+    match path.split_last() {
+        Some((Segment::Key(key), parents)) => Ok((key, parents)),
+        _ => Err("paths must end with a key".to_string()),
+    }
+}
+
+/// Find the table at `path`, creating missing tables if `create` is set
+fn toml_table<'a>(
+    item: &'a mut toml_edit::Item,
+    path: &[Segment],
+    create: bool,
+) -> std::result::Result<Option<&'a mut dyn toml_edit::TableLike>, String> {
+    let Some((first, rest)) = path.split_first() else {
+        return Ok(item.as_table_like_mut());
+    };
+    let next = match first {
+        Segment::Key(key) => {
+            let table = item
+                .as_table_like_mut()
+                .ok_or_else(|| format!("'{key}' is not inside a table"))?;
+            if table.get(key).is_none() {
+                if !create {
+                    return Ok(None);
+                }
+                table.insert(key, toml_edit::table());
+            }
+            table.get_mut(key).expect("just inserted")
+        }
+        Segment::Index(index) => match item
+            .as_array_of_tables_mut()
+            .and_then(|a| a.get_mut(*index))
+        {
+            Some(table) => return toml_table_in(table, rest, create),
+            None if create => return Err(format!("array index {index} does not exist")),
+            None => return Ok(None),
+        },
+    };
+    toml_table(next, rest, create)
+}
+
+fn toml_table_in<'a>(
+    table: &'a mut toml_edit::Table,
+    path: &[Segment],
+    create: bool,
+) -> std::result::Result<Option<&'a mut dyn toml_edit::TableLike>, String> {
+    let Some((Segment::Key(key), rest)) = path.split_first() else {
+        return match path.first() {
+            None => Ok(Some(table)),
+            Some(_) => Err("nested arrays are not supported in TOML files".to_string()),
+        };
+    };
+    if !table.contains_key(key) {
+        if !create {
+            return Ok(None);
+        }
+        table.insert(key, toml_edit::table());
+    }
+    toml_table(table.get_mut(key).expect("just inserted"), rest, create)
+}
+
+fn toml_value(value: &Value) -> std::result::Result<toml_edit::Value, String> {
+    Ok(match value {
+        Value::Null => return Err("TOML has no null value".to_string()),
+        Value::Bool(b) => (*b).into(),
+        Value::Number(n) => match n.as_i64() {
+            Some(i) => i.into(),
+            None => n.as_f64().unwrap_or_default().into(),
+        },
+        Value::String(s) => s.as_str().into(),
+        Value::Array(values) => {
+            let mut array = toml_edit::Array::new();
+            for value in values {
+                array.push(toml_value(value)?);
+            }
+            array.into()
+        }
+        Value::Object(map) => {
+            let mut table = toml_edit::InlineTable::new();
+            for (key, value) in map {
+                table.insert(key, toml_value(value)?);
+            }
+            table.into()
+        }
+    })
+}
+
+/// Line based editing of YAML block mappings, which keeps comments and formatting.
+/// Flow style collections and sequences can only be replaced as a whole.
+mod yaml {
+    use serde_json::Value;
+
+    use super::Segment;
+
+    type EditResult = std::result::Result<String, String>;
+
+    /// A `key: value` line
+    struct Entry {
+        line: usize,
+        indent: usize,
+        /// Text after the colon, without a trailing comment
+        value: String,
+    }
+
+    pub fn set(content: &str, path: &[Segment], value: &Value) -> EditResult {
+        let mut lines: Vec<String> = content.lines().map(|l| l.to_string()).collect();
+        let keys = keys(path)?;
+        check_single_document(&lines)?;
+        let (last, parents) = keys.split_last().expect("paths are never empty");
+
+        let (mut start, mut end) = (0, lines.len());
+        if is_sequence(&lines, start, end) {
+            return Err("the document is a sequence".to_string());
+        }
+        let mut indent = child_indent(&lines, start, end).unwrap_or(0);
+        for key in parents {
+            let line = match find(&lines, start, end, indent, key) {
+                Some(entry) if !entry.value.is_empty() => {
+                    return Err(format!("'{key}' is not a block mapping"));
+                }
+                Some(entry) => entry.line,
+                None => {
+                    let at = insert_position(&lines, start, end);
+                    lines.insert(at, format!("{}{}:", " ".repeat(indent), quote_key(key)));
+                    at
+                }
+            };
+            start = line + 1;
+            end = block_end(&lines, line, indent);
+            if is_sequence(&lines, start, end) {
+                return Err(format!("'{key}' is a sequence"));
+            }
+            indent = child_indent(&lines, start, end).unwrap_or(indent + 2);
+        }
+
+        let rendered = render(value);
+        match find(&lines, start, end, indent, last) {
+            Some(entry) => {
+                let original = lines[entry.line].clone();
+                let colon =
+                    entry.indent + key_end(&original[entry.indent..]).ok_or("invalid key")?;
+                let comment = comment(&original[colon + 1..]).unwrap_or("");
+                let block = block_end(&lines, entry.line, entry.indent);
+                lines[entry.line] = format!("{}: {rendered}{comment}", &original[..colon]);
+                // a nested block is replaced by the new value
+                lines.drain(entry.line + 1..block);
+            }
+            None => {
+                let at = insert_position(&lines, start, end);
+                lines.insert(
+                    at,
+                    format!("{}{}: {rendered}", " ".repeat(indent), quote_key(last)),
+                );
+            }
+        }
+        Ok(join(lines, content))
+    }
+
+    pub fn delete(content: &str, path: &[Segment]) -> EditResult {
+        let mut lines: Vec<String> = content.lines().map(|l| l.to_string()).collect();
+        let keys = keys(path)?;
+        check_single_document(&lines)?;
+
+        let (mut start, mut end) = (0, lines.len());
+        for (i, key) in keys.iter().enumerate() {
+            let Some(indent) = child_indent(&lines, start, end) else {
+                return Ok(content.to_string());
+            };
+            let Some(entry) = find(&lines, start, end, indent, key) else {
+                return Ok(content.to_string());
+            };
+            let block = block_end(&lines, entry.line, indent);
+            if i == keys.len() - 1 {
+                lines.drain(entry.line..block);
+            } else {
+                (start, end) = (entry.line + 1, block);
+            }
+        }
+        Ok(join(lines, content))
+    }
+
+    fn keys(path: &[Segment]) -> std::result::Result<Vec<&str>, String> {
+        path.iter()
+            .map(|s| match s {
+                Segment::Key(key) => Ok(key.as_str()),
+                Segment::Index(_) => {
+                    Err("array indices are not supported in YAML files".to_string())
+                }
+            })
+            .collect()
+    }
+
+    /// Keys are looked up in the first document, but new ones would end up in the last
+    fn check_single_document(lines: &[String]) -> std::result::Result<(), String> {
+        let is_separator = |l: &String| l.trim_end() == "---" || l.starts_with("--- ");
+        let first_content = lines.iter().position(|l| is_content(l) && !is_separator(l));
+        let separators = lines
+            .iter()
+            .enumerate()
+            .filter(|&(i, l)| is_separator(l) && first_content.is_some_and(|first| i > first))
+            .count();
+        if separators > 0 {
+            return Err("files with multiple YAML documents are not supported".to_string());
+        }
+        Ok(())
+    }
+
+    fn join(lines: Vec<String>, original: &str) -> String {
+        let mut output = lines.join("\n");
+        if original.ends_with('\n') || original.is_empty() {
+            output.push('\n');
+        }
+        output
+    }
+
+    fn is_content(line: &str) -> bool {
+        let trimmed = line.trim();
+        !trimmed.is_empty() && !trimmed.starts_with('#') && trimmed != "---"
+    }
+
+    fn indent_of(line: &str) -> usize {
+        line.len() - line.trim_start_matches(' ').len()
+    }
+
+    /// Indentation of the first content line in a range
+    fn child_indent(lines: &[String], start: usize, end: usize) -> Option<usize> {
+        lines[start..end]
+            .iter()
+            .find(|l| is_content(l))
+            .map(|l| indent_of(l))
+    }
+
+    /// Sequence items that are not indented further than their key belong to it
+    fn is_sequence_item(line: &str) -> bool {
+        let trimmed = line.trim_start_matches(' ');
+        trimmed == "-" || trimmed.starts_with("- ")
+    }
+
+    /// Whether the first content line in a range is a sequence item
+    fn is_sequence(lines: &[String], start: usize, end: usize) -> bool {
+        lines[start..end]
+            .iter()
+            .find(|l| is_content(l))
+            .is_some_and(|l| is_sequence_item(l))
+    }
+
+    /// First line after the block that starts at `line`
+    fn block_end(lines: &[String], line: usize, indent: usize) -> usize {
+        lines[line + 1..]
+            .iter()
+            .position(|l| {
+                is_content(l)
+                    && (indent_of(l) < indent || indent_of(l) == indent && !is_sequence_item(l))
+            })
+            .map(|p| line + 1 + p)
+            .unwrap_or(lines.len())
+    }
+
+    /// New entries go after the last content line of a range
+    fn insert_position(lines: &[String], start: usize, end: usize) -> usize {
+        lines[start..end]
+            .iter()
+            .rposition(|l| is_content(l))
+            .map(|p| start + p + 1)
+            .unwrap_or(start)
+    }
+
+    fn find(lines: &[String], start: usize, end: usize, indent: usize, key: &str) -> Option<Entry> {
+        (start..end).find_map(|i| {
+            let line = &lines[i];
+            if !is_content(line) || indent_of(line) != indent {
+                return None;
+            }
+            let rest = &line[indent..];
+            let colon = key_end(rest)?;
+            if unquote(rest[..colon].trim()) != key {
+                return None;
+            }
+            let after = &rest[colon + 1..];
+            let value = match comment(after) {
+                Some(comment) => &after[..after.len() - comment.len()],
+                None => after,
+            };
+            Some(Entry {
+                line: i,
+                indent,
+                value: value.trim().to_string(),
+            })
+        })
+    }
+
+    /// Position of the colon ending the key of a `key: value` line
+    fn key_end(line: &str) -> Option<usize> {
+        if line.starts_with('-') {
+            return None;
+        }
+        let mut quote = None;
+        for (i, c) in line.char_indices() {
+            match (quote, c) {
+                (Some(q), c) if c == q => quote = None,
+                (Some(_), _) => {}
+                (None, '"' | '\'') if i == 0 => quote = Some(c),
+                (None, ':') if line[i + 1..].is_empty() || line[i + 1..].starts_with(' ') => {
+                    return Some(i);
+                }
+                _ => {}
+            }
+        }
+        None
+    }
+
+    /// Trailing ` # comment` of a value, including the leading whitespace
+    fn comment(value: &str) -> Option<&str> {
+        let mut quote = None;
+        let mut previous = ' ';
+        for (i, c) in value.char_indices() {
+            match (quote, c) {
+                (Some(q), c) if c == q => quote = None,
+                (Some(_), _) => {}
+                (None, '"' | '\'') => quote = Some(c),
+                (None, '#') if previous == ' ' => {
+                    let start = value[..i].trim_end().len();
+                    return Some(&value[start..]);
+                }
+                _ => {}
+            }
+            previous = c;
+        }
+        None
+    }
+
+    fn unquote(key: &str) -> &str {
+        key.strip_prefix('"')
+            .and_then(|k| k.strip_suffix('"'))
+            .or_else(|| key.strip_prefix('\'').and_then(|k| k.strip_suffix('\'')))
+            .unwrap_or(key)
+    }
+
+    fn quote_key(key: &str) -> String {
+        if is_plain(key) {
+            key.to_string()
+        } else {
+            Value::String(key.to_string()).to_string()
+        }
+    }
+
+    /// Strings that can be written without quotes and are not read as another type
+    fn is_plain(s: &str) -> bool {
+        !s.is_empty()
+            && s.chars()
+                .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.' | '/'))
+            && !s.starts_with(['-', '.'])
+            && s.parse::<f64>().is_err()
+            && !matches!(
+                s.to_lowercase().as_str(),
+                "true" | "false" | "yes" | "no" | "on" | "off" | "null" | "~"
+            )
+    }
+
+    /// JSON is valid YAML, so anything that is not a plain string is written as JSON
+    fn render(value: &Value) -> String {
+        match value {
+            Value::String(s) if is_plain(s) => s.clone(),
+            value => value.to_string(),
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        fn path(keys: &[&str]) -> Vec<Segment> {
+            keys.iter().map(|k| Segment::Key(k.to_string())).collect()
+        }
+
+        const CONFIG: &str = "# CI config\nimage: node:18 # pinned\njobs:\n  test:\n    runs-on: ubuntu-22.04\n\n  lint:\n    runs-on: ubuntu-22.04\n";
+
+        #[test]
+        fn test_set_keeps_comments() {
+            let output = set(CONFIG, &path(&["image"]), &Value::from("node:20")).unwrap();
+            assert!(output.starts_with("# CI config\nimage: \"node:20\" # pinned\njobs:"));
+        }
+
+        #[test]
+        fn test_set_nested_and_new_keys() {
+            let output = set(
+                CONFIG,
+                &path(&["jobs", "lint", "runs-on"]),
+                &Value::from("ubuntu-24.04"),
+            )
+            .unwrap();
+            assert!(output.ends_with("  lint:\n    runs-on: ubuntu-24.04\n"));
+            assert!(output.contains("  test:\n    runs-on: ubuntu-22.04\n"));
+
+            let output = set(
+                CONFIG,
+                &path(&["jobs", "lint", "timeout"]),
+                &Value::from(10),
+            )
+            .unwrap();
+            assert!(output.ends_with("    runs-on: ubuntu-22.04\n    timeout: 10\n"));
+
+            let output = set(CONFIG, &path(&["env", "CI"]), &Value::from(true)).unwrap();
+            assert!(output.ends_with("    runs-on: ubuntu-22.04\nenv:\n  CI: true\n"));
+        }
+
+        #[test]
+        fn test_delete_removes_block() {
+            let output = delete(CONFIG, &path(&["jobs", "test"])).unwrap();
+            assert_eq!(
+                output,
+                "# CI config\nimage: node:18 # pinned\njobs:\n  lint:\n    runs-on: ubuntu-22.04\n"
+            );
+            assert_eq!(delete(CONFIG, &path(&["missing"])).unwrap(), CONFIG);
+        }
+
+        #[test]
+        fn test_refuses_multiple_documents() {
+            let value = Value::from("x");
+            assert!(set("a: 1\n---\nb: 2\n", &path(&["c"]), &value).is_err());
+            assert!(delete("a: 1\n--- # second\nb: 2\n", &path(&["a"])).is_err());
+            assert_eq!(
+                set("---\na: 1\n", &path(&["a"]), &value).unwrap(),
+                "---\na: x\n"
+            );
+        }
+
+        #[test]
+        fn test_set_refuses_sequences_and_flow_mappings() {
+            let value = Value::from("x");
+            for content in [
+                "steps:\n  - run: a\n",
+                "steps:\n- run: a\nname: b\n",
+                "steps: {run: a}\n",
+                "steps: [a]\n",
+            ] {
+                assert!(
+                    set(content, &path(&["steps", "run"]), &value).is_err(),
+                    "should refuse:\n{content}"
+                );
+            }
+            assert!(set("- a\n", &path(&["key"]), &value).is_err());
+
+            // a sequence is replaced as a whole, including items at the indentation of its key
+            assert_eq!(
+                set("steps:\n- a\n- b\nname: c\n", &path(&["steps"]), &value).unwrap(),
+                "steps: x\nname: c\n"
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_path() {
+        assert_eq!(
+            parse_path(r#".dependencies."lodash.merge""#).unwrap(),
+            vec![
+                Segment::Key("dependencies".to_string()),
+                Segment::Key("lodash.merge".to_string())
+            ]
+        );
+        assert_eq!(
+            parse_path(".bin[0].name").unwrap(),
+            vec![
+                Segment::Key("bin".to_string()),
+                Segment::Index(0),
+                Segment::Key("name".to_string())
+            ]
+        );
+        assert!(parse_path("engines").is_err());
+        assert!(parse_path(r#".dependencies."lodash.merge"#).is_err());
+        assert_eq!(split_assignment(r#"."a=b"=1"#), Some((r#"."a=b""#, "1")));
+    }
+
+    #[test]
+    fn test_edit_keeps_crlf() {
+        let content = "a: 1\r\nb: 2\r\n";
+        let edits = [Edit::Set(parse_path(".c").unwrap(), Value::from(3))];
+        assert_eq!(
+            edit_content(content, Format::Yaml, &edits).unwrap(),
+            "a: 1\r\nb: 2\r\nc: 3\r\n"
+        );
+        let content = "{\r\n  \"a\": 1\r\n}\r\n";
+        assert_eq!(
+            edit_content(content, Format::Json, &edits).unwrap(),
+            "{\r\n  \"a\": 1,\r\n  \"c\": 3\r\n}\r\n"
+        );
+    }
+
+    #[test]
+    fn test_edit_json_keeps_order_and_indent() {
+        let content =
+            "{\n    \"name\": \"a\",\n    \"engines\": {\n        \"node\": \">=18\"\n    }\n}\n";
+        let edits = [Edit::Set(
+            parse_path(".engines.node").unwrap(),
+            Value::from(">=20"),
+        )];
+        assert_eq!(
+            edit_json(content, &edits).unwrap(),
+            content.replace(">=18", ">=20")
+        );
+    }
+
+    #[test]
+    fn test_edit_toml_keeps_comments() {
+        let content =
+            "[package]\nname = \"a\" # the name\n\n# dependencies\n[dependencies]\nserde = \"1\"\n";
+        let edits = [
+            Edit::Set(parse_path(".package.name").unwrap(), Value::from("b")),
+            Edit::Delete(parse_path(".dependencies.serde").unwrap()),
+        ];
+        assert_eq!(
+            edit_toml(content, &edits).unwrap(),
+            "[package]\nname = \"b\" # the name\n\n# dependencies\n[dependencies]\n"
+        );
+    }
+}
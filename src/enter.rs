@@ -1,11 +1,14 @@
 use crate::dirs::get_data_local_dir;
 use crate::error::{NutError, Result};
+use crate::hooks::{self, Hook};
+use crate::workspace::Workspace;
 
-pub async fn enter(ulid: ulid::Ulid) -> Result<()> {
+pub async fn enter(workspace: &Workspace) -> Result<()> {
     let data_local_dir = get_data_local_dir().await?;
 
     // start shell in directory
-    let workspace_dir = data_local_dir.join(ulid.to_string());
+    let workspace_dir = data_local_dir.join(workspace.id.to_string());
+    hooks::run(Hook::Enter, workspace, &workspace_dir, &[]).await?;
 
     let shell = std::env::var("SHELL").unwrap_or("/bin/sh".to_string());
 
@@ -75,7 +75,7 @@ pub enum NutError {
     #[error("Invalid workspace ID: {id}")]
     #[diagnostic(
         code(nut::workspace::invalid_id),
-        help("Workspace IDs must be valid ULIDs")
+        help("Use a workspace ULID, a unique ULID prefix or a workspace name")
     )]
     InvalidWorkspaceId {
         id: String,
@@ -83,6 +83,43 @@ pub enum NutError {
         source: ulid::DecodeError,
     },
 
+    #[error("Workspace ID '{id}' is ambiguous")]
+    #[diagnostic(
+        code(nut::workspace::ambiguous_id),
+        help("Matching workspaces: {matches}. Use a longer prefix.")
+    )]
+    AmbiguousWorkspaceId { id: String, matches: String },
+
+    #[error("Workspace {id} not found")]
+    #[diagnostic(
+        code(nut::workspace::not_found),
+        help("List existing workspaces with 'nut list'")
+    )]
+    WorkspaceNotFound { id: String },
+
+    #[error("Invalid workspace name: '{name}'")]
+    #[diagnostic(
+        code(nut::workspace::invalid_name),
+        help(
+            "Names must start with a letter or digit, may only contain letters, digits, '-', '_' and '.', and must not be a valid ULID"
+        )
+    )]
+    InvalidWorkspaceName { name: String },
+
+    #[error("Workspace name '{name}' is already used by workspace {id}")]
+    #[diagnostic(
+        code(nut::workspace::name_taken),
+        help("Choose a different name or rename the other workspace first")
+    )]
+    WorkspaceNameTaken { name: String, id: String },
+
+    #[error("Invalid tag: '{tag}'")]
+    #[diagnostic(
+        code(nut::workspace::invalid_tag),
+        help("Tags must not be empty and must not contain whitespace or commas")
+    )]
+    InvalidTag { tag: String },
+
     #[error("Failed to get current directory")]
     #[diagnostic(code(nut::io::current_dir))]
     GetCurrentDirectoryFailed {
@@ -126,6 +163,16 @@ pub enum NutError {
         source: std::io::Error,
     },
 
+    #[error("Terminal error")]
+    #[diagnostic(
+        code(nut::ui::terminal_failed),
+        help("'nut ui' needs an interactive terminal")
+    )]
+    TerminalFailed {
+        #[source]
+        source: std::io::Error,
+    },
+
     #[error("GitHub API error")]
     #[diagnostic(code(nut::github::api_error))]
     GitHubApiError {
@@ -167,7 +214,9 @@ pub enum NutError {
     #[error("No command provided for apply")]
     #[diagnostic(
         code(nut::apply::missing_command),
-        help("Use 'nut apply -- <command>' or 'nut apply --script <path>'")
+        help(
+            "Use 'nut apply -- <command>', 'nut apply --script <path>' or 'nut apply --recipe <name>'"
+        )
     )]
     ApplyMissingCommand,
 
@@ -196,6 +245,207 @@ pub enum NutError {
         #[source]
         source: std::io::Error,
     },
+
+    #[error("Apply run {id} was aborted")]
+    #[diagnostic(
+        code(nut::apply::aborted),
+        help("Run the remaining repositories with 'nut apply --rerun-failed {id}'")
+    )]
+    ApplyAborted { id: String },
+
+    #[error("Push failed or was refused in {failed} repositories")]
+    #[diagnostic(code(nut::push::failed), help("See the RESULT column for the reason"))]
+    PushFailed { failed: usize },
+
+    #[error("Commit failed in {failed} repositories")]
+    #[diagnostic(
+        code(nut::commit::failed),
+        help("Their changes are still staged, fix the problems and run 'nut commit' again")
+    )]
+    CommitFailed { failed: usize },
+
+    #[error("Editing failed in {failed} repositories")]
+    #[diagnostic(
+        code(nut::edit::failed),
+        help("The reason is shown for each repository")
+    )]
+    EditFailed { failed: usize },
+
+    #[error("Checks failed in {failed} repositories")]
+    #[diagnostic(code(nut::check::failed), help("See the logs in {log_dir}"))]
+    CheckFailed { failed: usize, log_dir: String },
+
+    #[error("Unknown ecosystem: '{name}'")]
+    #[diagnostic(
+        code(nut::check::unknown_ecosystem),
+        help("Known ecosystems are rust, node, go, python and make")
+    )]
+    UnknownEcosystem { name: String },
+
+    #[error("Invalid check command: '{value}'")]
+    #[diagnostic(
+        code(nut::config::invalid_check_command),
+        help(
+            "Use --check-command ECOSYSTEM=COMMAND, e.g. --check-command 'rust=cargo clippy && cargo test'"
+        )
+    )]
+    InvalidCheckCommand { value: String },
+
+    #[error("Invalid dependency query: '{value}'")]
+    #[diagnostic(
+        code(nut::deps::invalid_query),
+        help("Use a name with an optional requirement, e.g. 'lodash<4.17.21' or 'serde>=1.0'")
+    )]
+    InvalidDependencyQuery { value: String },
+
+    #[error("Hook {hook} failed: {command} ({reason})")]
+    #[diagnostic(
+        code(nut::hook::failed),
+        help(
+            "Fix the hook, or remove it with: nut config --hook {hook}= or by deleting .nut/hooks/{hook}"
+        )
+    )]
+    HookFailed {
+        hook: &'static str,
+        command: String,
+        reason: String,
+    },
+
+    #[error("Unknown hook: {name}")]
+    #[diagnostic(
+        code(nut::hook::unknown),
+        help("Known hooks are post-import, pre-apply, post-apply and enter")
+    )]
+    UnknownHook { name: String },
+
+    #[error("Invalid hook: '{value}'")]
+    #[diagnostic(
+        code(nut::config::invalid_hook),
+        help(
+            "Use --hook HOOK=COMMAND, e.g. --hook 'post-import=git config user.email me@work.example'"
+        )
+    )]
+    InvalidHook { value: String },
+
+    #[error("Invalid git config override: '{value}'")]
+    #[diagnostic(
+        code(nut::config::invalid_git_config),
+        help(
+            "Use --git-config KEY=VALUE with a key like user.email, e.g. --git-config user.email=me@work.example"
+        )
+    )]
+    InvalidGitConfig { value: String },
+
+    #[error("{count} repositories do not use the git config overrides")]
+    #[diagnostic(
+        code(nut::doctor::problems_found),
+        help("Apply the overrides with: nut doctor --fix")
+    )]
+    DoctorFoundProblems { count: usize },
+
+    #[error("Recipes directory not configured")]
+    #[diagnostic(
+        code(nut::recipe::dir_not_configured),
+        help("Set the recipes directory using: nut config --recipes-dir <path>")
+    )]
+    RecipesDirectoryNotConfigured,
+
+    #[error("Recipe not found: {name}")]
+    #[diagnostic(
+        code(nut::recipe::not_found),
+        help("List available recipes with 'nut recipes list'")
+    )]
+    RecipeNotFound { name: String },
+
+    #[error("Recipe {recipe} requires missing tools: {tools}")]
+    #[diagnostic(
+        code(nut::recipe::tools_missing),
+        help("Install the tools and make sure they are on the PATH")
+    )]
+    RecipeToolsMissing { recipe: String, tools: String },
+
+    #[error("Run not found: {id}")]
+    #[diagnostic(
+        code(nut::apply::run_not_found),
+        help("List previous runs with 'nut runs list'")
+    )]
+    RunNotFound { id: String },
+
+    #[error("Invalid run {path}: {reason}")]
+    #[diagnostic(code(nut::apply::invalid_run))]
+    InvalidRun { path: PathBuf, reason: String },
+
+    #[error("No commit message provided")]
+    #[diagnostic(
+        code(nut::commit::missing_message),
+        help("Use 'nut commit -m <message>' or 'nut commit --message-file <path>'")
+    )]
+    CommitMessageMissing,
+
+    #[error("Search failed in repository {repo}: {message}")]
+    #[diagnostic(code(nut::grep::failed))]
+    GrepFailed { repo: String, message: String },
+
+    #[error("Invalid regular expression: {pattern}")]
+    #[diagnostic(
+        code(nut::replace::invalid_regex),
+        help("Use --fixed-strings to search for the pattern literally")
+    )]
+    InvalidRegex {
+        pattern: String,
+        #[source]
+        source: regex::Error,
+    },
+
+    #[error("Invalid glob: {glob}")]
+    #[diagnostic(code(nut::replace::invalid_glob))]
+    InvalidGlob {
+        glob: String,
+        #[source]
+        source: globset::Error,
+    },
+
+    #[error("Failed to read from stdin")]
+    #[diagnostic(code(nut::io::read_stdin))]
+    ReadStdinFailed {
+        #[source]
+        source: std::io::Error,
+    },
+
+    #[error("Invalid edit '{edit}': {reason}")]
+    #[diagnostic(
+        code(nut::edit::invalid),
+        help(
+            "Use --set '.path.to.key=VALUE' or --delete '.path.to.key', where VALUE is JSON or a plain string"
+        )
+    )]
+    InvalidEdit { edit: String, reason: String },
+
+    #[error("Unsupported file format: {file}")]
+    #[diagnostic(
+        code(nut::edit::unsupported_format),
+        help("Supported are .json, .toml, .yaml and .yml files")
+    )]
+    UnsupportedFileFormat { file: String },
+
+    #[error("Default branch of {repo} is unknown")]
+    #[diagnostic(
+        code(nut::git::default_branch_unknown),
+        help("Detect it with 'git remote set-head origin --auto' inside the repository")
+    )]
+    DefaultBranchUnknown { repo: String },
+
+    #[error("Failed to run pager: {pager}")]
+    #[diagnostic(
+        code(nut::output::pager_failed),
+        help("Check the core.pager git setting and the PAGER environment variable")
+    )]
+    PagerFailed {
+        pager: String,
+        #[source]
+        source: std::io::Error,
+    },
+
     #[error("GitHub token required")]
     #[diagnostic(code(nut::github::missing_token), help("{message}"))]
     MissingGitHubToken { message: String },
@@ -221,6 +471,64 @@ pub enum NutError {
         source: Box<dyn std::error::Error + Send + Sync>,
     },
 
+    #[error("Failed to read or write bundle: {path}")]
+    #[diagnostic(code(nut::bundle::archive_failed))]
+    BundleArchiveFailed {
+        path: PathBuf,
+        #[source]
+        source: std::io::Error,
+    },
+
+    #[error("Invalid bundle {path}: {reason}")]
+    #[diagnostic(
+        code(nut::bundle::invalid),
+        help("Bundles must be created with 'nut export'")
+    )]
+    InvalidBundle { path: PathBuf, reason: String },
+
+    #[error("Snapshot '{name}' already exists")]
+    #[diagnostic(
+        code(nut::snapshot::exists),
+        help(
+            "Choose a different name or delete the existing snapshot with 'nut snapshot delete {name}'"
+        )
+    )]
+    SnapshotExists { name: String },
+
+    #[error("Snapshot '{name}' not found")]
+    #[diagnostic(
+        code(nut::snapshot::not_found),
+        help("List existing snapshots with 'nut snapshot list'")
+    )]
+    SnapshotNotFound { name: String },
+
+    #[error("Invalid snapshot name: '{name}'")]
+    #[diagnostic(
+        code(nut::snapshot::invalid_name),
+        help(
+            "Names must start with a letter or digit and may only contain letters, digits, '-', '_' and '.'"
+        )
+    )]
+    InvalidSnapshotName { name: String },
+
+    #[error("Invalid operation log {path}: {reason}")]
+    #[diagnostic(code(nut::log::invalid))]
+    InvalidOperationLog { path: PathBuf, reason: String },
+
+    #[error("Nothing to undo")]
+    #[diagnostic(
+        code(nut::log::nothing_to_undo),
+        help("All logged operations have been undone already. See 'nut log' for details.")
+    )]
+    NothingToUndo,
+
+    #[error("Failed to serialize output")]
+    #[diagnostic(code(nut::output::serialize_failed))]
+    SerializeFailed {
+        #[source]
+        source: Box<dyn std::error::Error + Send + Sync>,
+    },
+
     #[error("HOME directory not found")]
     #[diagnostic(
         code(nut::config::home_not_found),
@@ -1,10 +1,12 @@
 use std::ffi::{OsStr, OsString};
-use std::os::unix::process::ExitStatusExt;
 use std::path::{Path, PathBuf};
 
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::ecosystem::{self, Ecosystem};
 use crate::error::{NutError, Result};
 use crate::{dirs, gh};
-use miette::IntoDiagnostic;
 
 pub struct RepoStatus {
     pub path_relative: OsString,
@@ -13,36 +15,50 @@ pub struct RepoStatus {
     pub staged_files: usize,
     pub untracked_files: usize,
     pub current_branch: String,
+    /// Commits ahead of and behind the upstream branch, if one is configured
+    pub ahead_behind: Option<(usize, usize)>,
+    /// Ecosystems detected from the manifest files, primary first
+    pub ecosystems: Vec<Ecosystem>,
+    /// Manifest files found in the repository root
+    pub manifests: Vec<&'static str>,
 }
 
 /// Helper to execute git commands with consistent error handling
-struct GitCommand<'a> {
+pub struct GitCommand<'a> {
     args: Vec<&'a str>,
+    envs: Vec<(&'a str, &'a OsStr)>,
     working_dir: &'a Path,
 }
 
 impl<'a> GitCommand<'a> {
-    fn new(working_dir: &'a Path) -> Self {
+    pub fn new(working_dir: &'a Path) -> Self {
         Self {
             args: Vec::new(),
+            envs: Vec::new(),
             working_dir,
         }
     }
 
-    fn arg(mut self, arg: &'a str) -> Self {
+    pub fn env(mut self, key: &'a str, value: &'a OsStr) -> Self {
+        self.envs.push((key, value));
+        self
+    }
+
+    pub fn arg(mut self, arg: &'a str) -> Self {
         self.args.push(arg);
         self
     }
 
-    fn args(mut self, args: &[&'a str]) -> Self {
+    pub fn args(mut self, args: &[&'a str]) -> Self {
         self.args.extend_from_slice(args);
         self
     }
 
-    async fn output(self) -> Result<std::process::Output> {
+    pub async fn output(self) -> Result<std::process::Output> {
         let output = tokio::process::Command::new("git")
             .current_dir(self.working_dir)
             .args(&self.args)
+            .envs(self.envs.iter().copied())
             .output()
             .await
             .map_err(|e| NutError::GitCommandFailed {
@@ -52,10 +68,11 @@ impl<'a> GitCommand<'a> {
         Ok(output)
     }
 
-    async fn run(self) -> Result<()> {
+    pub async fn run(self) -> Result<()> {
         let status = tokio::process::Command::new("git")
             .current_dir(self.working_dir)
             .args(&self.args)
+            .envs(self.envs.iter().copied())
             .status()
             .await
             .map_err(|e| NutError::GitCommandFailed {
@@ -71,10 +88,25 @@ impl<'a> GitCommand<'a> {
         Ok(())
     }
 
-    async fn output_string(self) -> Result<String> {
+    pub async fn output_string(self) -> Result<String> {
         let output = self.output().await?;
         Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
     }
+
+    /// Like `output`, but fails if git exits with a non-zero status
+    pub async fn checked_output(self) -> Result<Vec<u8>> {
+        let operation = format!("git {}", self.args.join(" "));
+        let output = self.output().await?;
+        if !output.status.success() {
+            return Err(NutError::GitOperationFailed { operation });
+        }
+        Ok(output.stdout)
+    }
+
+    pub async fn checked_output_string(self) -> Result<String> {
+        let output = self.checked_output().await?;
+        Ok(String::from_utf8_lossy(&output).trim().to_string())
+    }
 }
 
 /// Update an existing workspace repository if needed
@@ -134,20 +166,24 @@ async fn ensure_cache_repo(
                 .await?;
         }
     } else {
-        tokio::fs::create_dir_all(cache_dir).await.map_err(|e| {
-            NutError::CreateDirectoryFailed {
-                path: cache_dir.to_path_buf(),
-                source: e,
-            }
-        })?;
-        GitCommand::new(cache_dir)
-            .args(&["clone", clone_url, full_name, "--mirror", "--bare"])
-            .run()
-            .await?;
+        create_cache_repo(cache_dir, full_name, clone_url).await?;
     }
     Ok(())
 }
 
+async fn create_cache_repo(cache_dir: &Path, full_name: &str, clone_url: &str) -> Result<()> {
+    tokio::fs::create_dir_all(cache_dir)
+        .await
+        .map_err(|e| NutError::CreateDirectoryFailed {
+            path: cache_dir.to_path_buf(),
+            source: e,
+        })?;
+    GitCommand::new(cache_dir)
+        .args(&["clone", clone_url, full_name, "--mirror", "--bare"])
+        .run()
+        .await
+}
+
 /// Clone from cache to workspace
 async fn clone_from_cache_to_workspace(
     workspace_dir: &Path,
@@ -203,6 +239,9 @@ pub async fn clone(
             latest_commit,
         )
         .await?;
+    } else if !workspace_dir.join(full_name).exists() && !cache_dir.join(full_name).exists() {
+        // without commit info there is nothing to update, but the cache is still needed
+        create_cache_repo(&cache_dir, full_name, &clone_url).await?;
     }
 
     // Repository might already exist (e.g., empty repo)
@@ -264,9 +303,11 @@ pub async fn get_repo_status(
         }
     }
 
-    // Get git status porcelain output
+    // Get git status porcelain output. Without the optional index refresh, git status
+    // doesn't take index.lock, so git commands the user runs at the same time don't fail
     let status_output = tokio::process::Command::new("git")
         .current_dir(&abs_path)
+        .env("GIT_OPTIONAL_LOCKS", "0")
         .arg("status")
         .arg("--porcelain")
         .output()
@@ -319,6 +360,20 @@ pub async fn get_repo_status(
 
     let has_changes = modified_files > 0 || staged_files > 0 || untracked_files > 0;
 
+    // Compare with upstream, if there is one
+    let ahead_behind = GitCommand::new(&abs_path)
+        .args(&["rev-list", "--left-right", "--count", "HEAD...@{upstream}"])
+        .checked_output_string()
+        .await
+        .ok()
+        .and_then(|counts| {
+            let (ahead, behind) = counts.split_once('\t')?;
+            Some((ahead.parse().ok()?, behind.parse().ok()?))
+        });
+
+    let manifests = ecosystem::find_manifests(&abs_path);
+    let ecosystems = Ecosystem::detect_all(&abs_path);
+
     Some(RepoStatus {
         path_relative: repo_path_relative.clone().into_os_string(),
         has_changes,
@@ -326,6 +381,12 @@ pub async fn get_repo_status(
         staged_files,
         untracked_files,
         current_branch,
+        ahead_behind,
+        ecosystems,
+        manifests: manifests
+            .into_iter()
+            .map(|(_, manifest)| manifest)
+            .collect(),
     })
 }
 
@@ -354,7 +415,7 @@ pub async fn get_all_repos_status(workspace_dir: &Path) -> Result<Vec<RepoStatus
 ///
 /// Searches for directories containing a `.git` subdirectory within the workspace,
 /// up to a maximum depth of 3 levels. Returns a sorted list of repository paths.
-fn find_repositories(workspace_dir: &Path) -> Result<Vec<PathBuf>> {
+pub fn find_repositories(workspace_dir: &Path) -> Result<Vec<PathBuf>> {
     let mut repos = Vec::new();
 
     let walker = walkdir::WalkDir::new(workspace_dir)
@@ -380,55 +441,100 @@ fn find_repositories(workspace_dir: &Path) -> Result<Vec<PathBuf>> {
     Ok(repos)
 }
 
-/// Execute a command in each repository without using a subshell.
-///
-/// Discovers all git repositories in the workspace and executes the specified command
-/// in each one. The command is executed directly (not in a shell).
-pub async fn apply_command(workspace_dir: &Path, command: Vec<&OsStr>) -> Result<()> {
-    let repos = find_repositories(workspace_dir)?;
+/// Current branch and commit of a repository
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HeadState {
+    pub branch: Option<String>,
+    pub commit: Option<String>,
+}
 
-    if repos.is_empty() {
-        println!("No repositories found in workspace");
-        return Ok(());
-    }
+pub async fn head_state(repo_dir: &Path) -> HeadState {
+    let branch = GitCommand::new(repo_dir)
+        .args(&["branch", "--show-current"])
+        .checked_output_string()
+        .await
+        .ok()
+        .filter(|b| !b.is_empty());
+    let commit = GitCommand::new(repo_dir)
+        .args(&["rev-parse", "--verify", "--quiet", "HEAD"])
+        .checked_output_string()
+        .await
+        .ok();
+    HeadState { branch, commit }
+}
 
-    // Execute command in each repository
-    let command_name = &command[0];
-    let args = &command[1..];
+/// Default branch of the `origin` remote, as recorded in `refs/remotes/origin/HEAD`
+pub async fn remote_default_branch(repo_dir: &Path) -> Option<String> {
+    GitCommand::new(repo_dir)
+        .args(&["symbolic-ref", "--short", "refs/remotes/origin/HEAD"])
+        .checked_output_string()
+        .await
+        .ok()?
+        .strip_prefix("origin/")
+        .map(|b| b.to_string())
+}
 
-    for repo_path_relative in repos {
-        println!("==> {} <==", repo_path_relative.display());
+/// Default branch of the `origin` remote, asking the remote if `refs/remotes/origin/HEAD`
+/// is missing, e.g. in shallow clones or repositories set up with `git init` and fetch
+pub async fn query_remote_default_branch(repo_dir: &Path) -> Option<String> {
+    if let Some(branch) = remote_default_branch(repo_dir).await {
+        return Some(branch);
+    }
+    // prints `ref: refs/heads/main<TAB>HEAD` followed by the commit
+    GitCommand::new(repo_dir)
+        .args(&["ls-remote", "--symref", "origin", "HEAD"])
+        .checked_output_string()
+        .await
+        .ok()?
+        .lines()
+        .find_map(|line| {
+            line.strip_prefix("ref: refs/heads/")?
+                .strip_suffix("\tHEAD")
+        })
+        .map(|b| b.to_string())
+}
 
-        let status = tokio::process::Command::new(command_name)
-            .args(args)
-            .current_dir(workspace_dir.join(&repo_path_relative))
-            .status()
-            .await
-            .map_err(|e| NutError::CommandFailed {
-                repo: repo_path_relative.display().to_string(),
-                source: e,
-            })?;
+/// Most recent activity in a repository.
+///
+/// Uses the newest of the HEAD commit time, the index modification time and the
+/// modification times of files with uncommitted changes.
+pub async fn last_activity(repo_dir: &Path) -> Option<DateTime<Utc>> {
+    let mut times: Vec<DateTime<Utc>> = Vec::new();
 
-        if !status.success() {
-            // render the error using miette
-            let error: miette::Result<()> = Err(NutError::CommandFailed {
-                repo: repo_path_relative.display().to_string(),
-                source: std::io::Error::other(if let Some(code) = status.code() {
-                    format!("Command exited with status code {}", code)
-                } else if let Some(signal) = status.signal() {
-                    format!("Command terminated by signal {}", signal)
-                } else {
-                    "Command terminated for unknown reason".to_string()
-                }),
-            })
-            .into_diagnostic();
-
-            // this will automatically render fancy miette errors due to global hook in main.rs
-            eprintln!();
-            eprintln!("{:?}", error.err().unwrap());
+    let commit_time = GitCommand::new(repo_dir)
+        .args(&["log", "-1", "--format=%ct"])
+        .output_string()
+        .await
+        .ok()
+        .and_then(|s| s.parse::<i64>().ok())
+        .and_then(|secs| DateTime::from_timestamp(secs, 0));
+    times.extend(commit_time);
+
+    let mut modified_paths = vec![repo_dir.join(".git/index")];
+    if let Ok(output) = GitCommand::new(repo_dir)
+        .args(&["status", "--porcelain", "-z"])
+        .output()
+        .await
+    {
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        let mut entries = stdout.split('\0');
+        while let Some(entry) = entries.next() {
+            if entry.len() < 4 {
+                continue;
+            }
+            modified_paths.push(repo_dir.join(&entry[3..]));
+            // renames and copies are followed by the original path
+            if entry.starts_with('R') || entry.starts_with('C') {
+                entries.next();
+            }
         }
-        println!();
     }
 
-    Ok(())
+    for path in modified_paths {
+        if let Ok(modified) = tokio::fs::metadata(&path).await.and_then(|m| m.modified()) {
+            times.push(modified.into());
+        }
+    }
+
+    times.into_iter().max()
 }
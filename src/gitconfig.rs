@@ -0,0 +1,136 @@
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use crate::config::NutConfig;
+use crate::error::{NutError, Result};
+use crate::git::GitCommand;
+use crate::workspace::Workspace;
+
+/// A git config value that differs from its override in a repository
+pub struct Mismatch {
+    pub key: String,
+    pub expected: String,
+    /// All values of the key, empty if it is not set
+    pub actual: Vec<String>,
+}
+
+/// Git config overrides for the repositories of a workspace: the ones from the nut config,
+/// replaced by the ones in `.nut/gitconfig` of the workspace
+pub async fn overrides(workspace: &Workspace) -> Result<BTreeMap<String, String>> {
+    let config = NutConfig::load()?;
+    let mut overrides: BTreeMap<_, _> = config
+        .git_config
+        .iter()
+        .map(|(key, value)| (canonical_key(key), value.clone()))
+        .collect();
+
+    let path = workspace_file(workspace);
+    if path.exists() {
+        let path = path.to_str().ok_or(NutError::InvalidUtf8)?;
+        let list = GitCommand::new(&workspace.path)
+            .args(&["config", "--file", path, "--list"])
+            .checked_output_string()
+            .await?;
+        for line in list.lines() {
+            let (key, value) = line.split_once('=').unwrap_or((line, ""));
+            overrides.insert(canonical_key(key), value.to_string());
+        }
+    }
+    Ok(overrides)
+}
+
+/// Set or, without a value, remove an override in `.nut/gitconfig` of the workspace
+pub async fn set_workspace_override(
+    workspace: &Workspace,
+    key: &str,
+    value: Option<&str>,
+) -> Result<()> {
+    let path = workspace_file(workspace);
+    let path = path.to_str().ok_or(NutError::InvalidUtf8)?;
+    let command = GitCommand::new(&workspace.path).args(&["config", "--file", path]);
+    let output = match value {
+        Some(value) => {
+            command
+                .args(&["--replace-all", key, value])
+                .output()
+                .await?
+        }
+        None => command.args(&["--unset-all", key]).output().await?,
+    };
+    // exit code 5 means there was nothing to unset
+    let nothing_to_unset = value.is_none() && output.status.code() == Some(5);
+    if output.status.success() || nothing_to_unset {
+        return Ok(());
+    }
+    Err(NutError::InvalidGitConfig {
+        value: key.to_string(),
+    })
+}
+
+/// Overrides whose value is different in the repository
+pub async fn mismatches(
+    repo_dir: &Path,
+    overrides: &BTreeMap<String, String>,
+) -> Result<Vec<Mismatch>> {
+    let mut mismatches = Vec::new();
+    for (key, expected) in overrides {
+        let values = GitCommand::new(repo_dir)
+            .args(&["config", "--get-all", key])
+            .output_string()
+            .await?;
+        let actual: Vec<_> = values.lines().map(|v| v.to_string()).collect();
+        if actual != [expected.as_str()] {
+            mismatches.push(Mismatch {
+                key: key.clone(),
+                expected: expected.clone(),
+                actual,
+            });
+        }
+    }
+    Ok(mismatches)
+}
+
+/// Write the overrides to the local config of a repository
+pub async fn apply(repo_dir: &Path, overrides: &BTreeMap<String, String>) -> Result<()> {
+    for (key, value) in overrides {
+        GitCommand::new(repo_dir)
+            .args(&["config", "--local", "--replace-all", key, value])
+            .checked_output()
+            .await?;
+    }
+    Ok(())
+}
+
+fn workspace_file(workspace: &Workspace) -> PathBuf {
+    workspace.metadata_dir().join("gitconfig")
+}
+
+/// Section and variable names are case-insensitive in git, subsections are not,
+/// so `User.signingKey` and `user.signingkey` are the same key
+pub fn canonical_key(key: &str) -> String {
+    let Some((section, rest)) = key.split_once('.') else {
+        return key.to_lowercase();
+    };
+    match rest.rsplit_once('.') {
+        Some((subsection, name)) => format!(
+            "{}.{subsection}.{}",
+            section.to_lowercase(),
+            name.to_lowercase()
+        ),
+        None => format!("{}.{}", section.to_lowercase(), rest.to_lowercase()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_canonicalizes_keys() {
+        assert_eq!(canonical_key("User.signingKey"), "user.signingkey");
+        assert_eq!(
+            canonical_key("url.git@GitHub.com:.insteadOf"),
+            "url.git@GitHub.com:.insteadof"
+        );
+    }
+}
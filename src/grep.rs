@@ -0,0 +1,126 @@
+use std::path::Path;
+
+use serde::Serialize;
+
+use crate::error::{NutError, Result};
+use crate::git::{self, GitCommand};
+use crate::output::{OutputFormat, print_json};
+use crate::workspace::Workspace;
+
+pub struct GrepOptions {
+    pub pattern: String,
+    pub ignore_case: bool,
+    pub fixed_strings: bool,
+    /// Only print the paths of matching files
+    pub files_with_matches: bool,
+    /// Limit the search to these pathspecs
+    pub pathspec: Vec<String>,
+    pub format: OutputFormat,
+}
+
+#[derive(Serialize)]
+struct Match {
+    repo: String,
+    path: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    line: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    text: Option<String>,
+}
+
+/// Search all repositories in parallel using `git grep`, which respects `.gitignore`
+pub async fn grep(workspace: &Workspace, options: &GrepOptions) -> Result<()> {
+    let repos = git::find_repositories(&workspace.path)?;
+    let futures = repos
+        .iter()
+        .map(|repo| grep_repo(&workspace.path, repo, options));
+    let results = futures_util::future::join_all(futures).await;
+
+    let mut matches = Vec::new();
+    for result in results {
+        matches.extend(result?);
+    }
+
+    if options.format == OutputFormat::Json {
+        return print_json(&matches);
+    }
+    for m in matches {
+        match (m.line, m.text) {
+            (Some(line), Some(text)) => println!("{}/{}:{line}:{text}", m.repo, m.path),
+            _ => println!("{}/{}", m.repo, m.path),
+        }
+    }
+    Ok(())
+}
+
+async fn grep_repo(
+    workspace_path: &Path,
+    repo: &Path,
+    options: &GrepOptions,
+) -> Result<Vec<Match>> {
+    // -z separates path, line number and text with NUL, so paths containing ':' parse correctly
+    let repo_dir = workspace_path.join(repo);
+    let mut command = GitCommand::new(&repo_dir).args(&["grep", "--untracked", "-I", "-z"]);
+    command = if options.files_with_matches {
+        command.arg("--files-with-matches")
+    } else {
+        command.arg("--line-number")
+    };
+    if options.ignore_case {
+        command = command.arg("--ignore-case");
+    }
+    if options.fixed_strings {
+        command = command.arg("--fixed-strings");
+    }
+    command = command.args(&["-e", &options.pattern, "--"]);
+    for pathspec in &options.pathspec {
+        command = command.arg(pathspec);
+    }
+
+    let output = command.output().await?;
+    // exit code 1 means nothing matched
+    match output.status.code() {
+        Some(0) => {}
+        Some(1) => return Ok(Vec::new()),
+        _ => {
+            return Err(NutError::GrepFailed {
+                repo: repo.display().to_string(),
+                message: String::from_utf8_lossy(&output.stderr).trim().to_string(),
+            });
+        }
+    }
+
+    let repo = repo.to_string_lossy().to_string();
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    if options.files_with_matches {
+        return Ok(stdout
+            .split('\0')
+            .filter(|p| !p.is_empty())
+            .map(|path| Match {
+                repo: repo.clone(),
+                path: path.to_string(),
+                line: None,
+                text: None,
+            })
+            .collect());
+    }
+
+    Ok(stdout
+        .lines()
+        .filter_map(|l| parse_line(&repo, l))
+        .collect())
+}
+
+/// Parse a `path\0line\0text` line of `git grep -z --line-number`
+fn parse_line(repo: &str, line: &str) -> Option<Match> {
+    let mut parts = line.splitn(3, '\0');
+    let path = parts.next()?;
+    let line_number = parts.next()?.parse().ok()?;
+    let text = parts.next()?;
+    Some(Match {
+        repo: repo.to_string(),
+        path: path.to_string(),
+        line: Some(line_number),
+        text: Some(text.to_string()),
+    })
+}
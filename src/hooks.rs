@@ -0,0 +1,114 @@
+use std::ffi::OsString;
+use std::fmt;
+use std::os::unix::fs::PermissionsExt;
+use std::path::Path;
+
+use crate::apply;
+use crate::config::NutConfig;
+use crate::error::{NutError, Result};
+use crate::workspace::Workspace;
+
+/// Points where nut runs the commands configured in `~/.nut.json` and the scripts in
+/// `.nut/hooks/` of the workspace
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Hook {
+    /// In a repository after it was cloned into the workspace
+    PostImport,
+    /// In the workspace before 'nut apply' runs the command in any repository
+    PreApply,
+    /// In the workspace after 'nut apply' finished, unless it was aborted
+    PostApply,
+    /// In the workspace before the shell of 'nut enter' starts
+    Enter,
+}
+
+impl Hook {
+    pub const ALL: [Hook; 4] = [
+        Hook::PostImport,
+        Hook::PreApply,
+        Hook::PostApply,
+        Hook::Enter,
+    ];
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|h| h.name() == name)
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Hook::PostImport => "post-import",
+            Hook::PreApply => "pre-apply",
+            Hook::PostApply => "post-apply",
+            Hook::Enter => "enter",
+        }
+    }
+}
+
+impl fmt::Display for Hook {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// Run the configured command and then the workspace script of a hook in `dir`, if any.
+/// Output goes to the terminal, and a failing hook fails the operation
+pub async fn run(
+    hook: Hook,
+    workspace: &Workspace,
+    dir: &Path,
+    env: &[(&str, OsString)],
+) -> Result<()> {
+    let config = NutConfig::load()?;
+    let mut commands = Vec::new();
+    if let Some(command) = config.hooks.get(hook.name()) {
+        commands.push(apply::shell_command(command)?);
+    }
+    let script = workspace.path.join(".nut").join("hooks").join(hook.name());
+    if let Ok(metadata) = std::fs::metadata(&script) {
+        if metadata.is_file() && metadata.permissions().mode() & 0o111 != 0 {
+            commands.push(vec![script.into_os_string()]);
+        } else {
+            eprintln!(
+                "Ignoring {}, it is not executable",
+                script.to_string_lossy()
+            );
+        }
+    }
+
+    for command in commands {
+        let status = tokio::process::Command::new(&command[0])
+            .args(&command[1..])
+            .current_dir(dir)
+            .env("NUT_HOOK", hook.name())
+            .env("NUT_WORKSPACE_ID", workspace.id.to_string())
+            .env("NUT_WORKSPACE_DIR", &workspace.path)
+            .envs(env.iter().map(|(key, value)| (key, value)))
+            .status()
+            .await;
+        let reason = match status {
+            Ok(status) if status.success() => continue,
+            Ok(status) => apply::describe_exit_status(&status),
+            Err(e) => e.to_string(),
+        };
+        return Err(NutError::HookFailed {
+            hook: hook.name(),
+            command: command
+                .iter()
+                .map(|a| a.to_string_lossy())
+                .collect::<Vec<_>>()
+                .join(" "),
+            reason,
+        });
+    }
+    Ok(())
+}
+
+/// Run the post-import hook in a repository
+pub async fn post_import(workspace: &Workspace, repo: &Path) -> Result<()> {
+    let repo_dir = workspace.path.join(repo);
+    let env = [
+        ("NUT_REPO", repo.as_os_str().to_os_string()),
+        ("NUT_REPO_DIR", repo_dir.clone().into_os_string()),
+    ];
+    run(Hook::PostImport, workspace, &repo_dir, &env).await
+}
@@ -0,0 +1,242 @@
+use std::cmp::{Ordering, Reverse};
+use std::path::Path;
+
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+
+use crate::error::Result;
+use crate::git;
+use crate::output::{OutputFormat, human_size, print_json};
+use crate::workspace::Workspace;
+
+/// Optional columns for `nut list`, which are more expensive to compute
+#[derive(clap::ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Column {
+    /// Number of repositories
+    Repos,
+    /// Number of repositories with uncommitted changes
+    Dirty,
+    /// Time of the most recent commit or file modification
+    Activity,
+    /// Disk usage of the workspace directory
+    Size,
+}
+
+#[derive(clap::ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum SortKey {
+    /// Creation time, most recent first
+    #[default]
+    Created,
+    /// Last activity, most recent first
+    Activity,
+    /// Workspace name, unnamed workspaces last
+    Name,
+    /// Number of repositories, most first
+    Repos,
+}
+
+pub struct ListOptions<'a> {
+    pub archived: bool,
+    pub tags: &'a [String],
+    pub columns: &'a [Column],
+    pub sort: SortKey,
+    pub limit: Option<usize>,
+    pub format: OutputFormat,
+}
+
+#[derive(Serialize)]
+struct WorkspaceEntry {
+    id: String,
+    name: Option<String>,
+    description: Option<String>,
+    created: DateTime<Utc>,
+    tags: Vec<String>,
+    archived: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    repos: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    dirty_repos: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    last_activity: Option<DateTime<Utc>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    disk_usage: Option<u64>,
+    /// Why a column couldn't be computed
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+pub async fn list(options: ListOptions<'_>) -> Result<()> {
+    // sorting by activity or repositories needs that column for every workspace,
+    // the other columns are only computed for the workspaces that are shown
+    let sort_column = match options.sort {
+        SortKey::Activity => Some(Column::Activity),
+        SortKey::Repos => Some(Column::Repos),
+        SortKey::Created | SortKey::Name => None,
+    };
+
+    let mut rows = Vec::new();
+    for workspace in Workspace::all().await? {
+        let archived = workspace.is_archived().await;
+        if archived && !options.archived {
+            continue;
+        }
+        let tags = workspace.tags().await;
+        if !options.tags.iter().all(|t| tags.contains(t)) {
+            continue;
+        }
+
+        let mut entry = WorkspaceEntry {
+            id: workspace.id.to_string(),
+            name: workspace.name().await,
+            description: workspace.description().await,
+            created: workspace.id.datetime().into(),
+            tags,
+            archived,
+            repos: None,
+            dirty_repos: None,
+            last_activity: None,
+            disk_usage: None,
+            error: None,
+        };
+        if let Some(column) = sort_column {
+            compute_column(&workspace, &mut entry, column).await;
+        }
+        rows.push((workspace, entry));
+    }
+
+    match options.sort {
+        SortKey::Created => rows.sort_by_key(|(_, e)| Reverse(e.created)),
+        SortKey::Activity => rows.sort_by_key(|(_, e)| Reverse(e.last_activity)),
+        SortKey::Name => rows.sort_by(|(_, a), (_, b)| match (&a.name, &b.name) {
+            (Some(a), Some(b)) => a.cmp(b),
+            (Some(_), None) => Ordering::Less,
+            (None, Some(_)) => Ordering::Greater,
+            (None, None) => b.created.cmp(&a.created),
+        }),
+        SortKey::Repos => rows.sort_by_key(|(_, e)| Reverse(e.repos)),
+    }
+
+    if let Some(limit) = options.limit {
+        rows.truncate(limit);
+    }
+
+    let mut entries = Vec::new();
+    for (workspace, mut entry) in rows {
+        for &column in options.columns {
+            if Some(column) != sort_column {
+                compute_column(&workspace, &mut entry, column).await;
+            }
+        }
+        entries.push(entry);
+    }
+
+    match options.format {
+        OutputFormat::Json => print_json(&entries)?,
+        OutputFormat::Text => {
+            for entry in entries {
+                print_entry(&entry, options.columns);
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Fill in one column of an entry. A failure is recorded in the entry instead
+/// of aborting the whole listing
+async fn compute_column(workspace: &Workspace, entry: &mut WorkspaceEntry, column: Column) {
+    let result = match column {
+        Column::Repos => {
+            git::find_repositories(&workspace.path).map(|repos| entry.repos = Some(repos.len()))
+        }
+        Column::Dirty => git::get_all_repos_status(&workspace.path)
+            .await
+            .map(|statuses| {
+                entry.dirty_repos = Some(statuses.iter().filter(|s| s.has_changes).count())
+            }),
+        Column::Activity => last_activity(&workspace.path)
+            .await
+            .map(|time| entry.last_activity = time),
+        Column::Size => {
+            // walking a large workspace blocks, so keep it off the async threads
+            let path = workspace.path.clone();
+            entry.disk_usage = tokio::task::spawn_blocking(move || disk_usage(&path))
+                .await
+                .ok();
+            Ok(())
+        }
+    };
+    if let Err(e) = result {
+        entry.error = Some(e.to_string());
+    }
+}
+
+async fn last_activity(workspace_dir: &Path) -> Result<Option<DateTime<Utc>>> {
+    let futures = git::find_repositories(workspace_dir)?
+        .into_iter()
+        .map(|repo| async move { git::last_activity(&workspace_dir.join(repo)).await });
+    Ok(futures_util::future::join_all(futures)
+        .await
+        .into_iter()
+        .flatten()
+        .max())
+}
+
+fn print_entry(entry: &WorkspaceEntry, columns: &[Column]) {
+    match &entry.name {
+        Some(name) => println!("{} ({})", entry.id, name),
+        None => println!("{}", entry.id),
+    }
+    println!("  Created: {}", entry.created.format("%Y-%m-%d %H:%M:%S"));
+    if !entry.tags.is_empty() {
+        println!("  Tags: {}", entry.tags.join(", "));
+    }
+    if entry.archived {
+        println!("  Archived");
+    }
+    for column in columns {
+        match column {
+            Column::Repos => match (entry.repos, &entry.error) {
+                (None, Some(error)) => println!("  Repositories: (failed: {error})"),
+                (repos, _) => println!("  Repositories: {}", repos.unwrap_or_default()),
+            },
+            Column::Dirty => match (entry.dirty_repos, &entry.error) {
+                (None, Some(error)) => println!("  Repositories with changes: (failed: {error})"),
+                (dirty, _) => {
+                    println!("  Repositories with changes: {}", dirty.unwrap_or_default())
+                }
+            },
+            Column::Activity => match (entry.last_activity, &entry.error) {
+                (Some(time), _) => {
+                    println!("  Last activity: {}", time.format("%Y-%m-%d %H:%M:%S"))
+                }
+                (None, Some(error)) => println!("  Last activity: (failed: {error})"),
+                (None, None) => println!("  Last activity: (none)"),
+            },
+            Column::Size => {
+                println!(
+                    "  Disk usage: {}",
+                    human_size(entry.disk_usage.unwrap_or_default())
+                );
+            }
+        }
+    }
+    println!(
+        "  {}",
+        entry
+            .description
+            .as_deref()
+            .unwrap_or("(missing description)")
+    );
+    println!();
+}
+
+fn disk_usage(path: &Path) -> u64 {
+    walkdir::WalkDir::new(path)
+        .into_iter()
+        .filter_map(|e| e.ok())
+        .filter(|e| e.file_type().is_file())
+        .filter_map(|e| e.metadata().ok())
+        .map(|m| m.len())
+        .sum()
+}
@@ -1,21 +1,44 @@
+mod apply;
+mod bundle;
+mod check;
+mod commit;
 mod config;
+mod deps;
+mod diff;
 mod dirs;
+mod doctor;
+mod ecosystem;
+mod edit;
 mod enter;
 mod error;
 mod gh;
 mod git;
+mod gitconfig;
+mod grep;
+mod hooks;
+mod list;
+mod oplog;
+mod output;
+mod prompt;
+mod push;
+mod recipe;
+mod replace;
+mod runs;
+mod snapshot;
+mod status;
+mod ui;
 mod workspace;
 
-use std::ffi::OsStr;
+use std::ffi::OsString;
 use std::io::{Write, stdout};
 
-use chrono::{DateTime, Utc};
+use chrono::Utc;
 use clap::{Parser, Subcommand};
 use miette::{IntoDiagnostic, Result};
-use ulid::Ulid;
 
 use crate::dirs::{get_cache_dir, get_data_local_dir};
 use crate::error::NutError;
+use crate::output::OutputFormat;
 use crate::workspace::Workspace;
 
 #[derive(Parser)]
@@ -36,24 +59,298 @@ enum Commands {
         /// lists test values
         #[arg(short, long)]
         description: String,
+
+        /// Short name that can be used instead of the workspace ID
+        #[arg(short, long)]
+        name: Option<String>,
     },
     /// Enter an existing workspace
     Enter {
-        /// Workspace ID
+        /// Workspace ID, name or unique ID prefix
+        id: String,
+    },
+    /// Set the name of a workspace
+    Rename {
+        /// Workspace ID, name or unique ID prefix
+        id: String,
+
+        /// New name for the workspace
+        name: String,
+    },
+    /// Change the description of a workspace
+    Describe {
+        /// Workspace ID, name or unique ID prefix
+        id: String,
+
+        /// New description
+        description: String,
+    },
+    /// Add or remove workspace tags
+    Tag {
+        /// Workspace ID, name or unique ID prefix
+        id: String,
+
+        /// Tags to add (or remove with --remove)
+        #[arg(required = true)]
+        tags: Vec<String>,
+
+        /// Remove the given tags instead of adding them
+        #[arg(short, long)]
+        remove: bool,
+    },
+    /// Archive a workspace, hiding it from `nut list`
+    Archive {
+        /// Workspace ID, name or unique ID prefix
+        id: String,
+    },
+    /// Unarchive a workspace
+    Unarchive {
+        /// Workspace ID, name or unique ID prefix
         id: String,
     },
     /// List existing workspaces
-    List {},
+    List {
+        /// Include archived workspaces
+        #[arg(short, long)]
+        archived: bool,
+
+        /// Only show workspaces with this tag (can be repeated)
+        #[arg(short, long)]
+        tag: Vec<String>,
+
+        /// Additional columns to show, comma separated
+        #[arg(short, long, value_delimiter = ',')]
+        columns: Vec<list::Column>,
+
+        /// Sort order
+        #[arg(short, long, value_enum, default_value_t)]
+        sort: list::SortKey,
+
+        /// Only show the first N workspaces
+        #[arg(short, long)]
+        limit: Option<usize>,
+
+        /// Output format
+        #[arg(short, long, value_enum, default_value_t)]
+        format: OutputFormat,
+    },
     /// Show status of a workspace
     Status {
-        /// Workspace ID
+        /// Workspace ID or name
+        /// If not provided, uses the currently entered workspace
+        #[arg(short, long)]
+        workspace: Option<String>,
+
+        /// Show all repositories with their ecosystems and manifest files
+        #[arg(short, long)]
+        long: bool,
+
+        /// Output format
+        #[arg(short, long, value_enum, default_value_t)]
+        format: OutputFormat,
+    },
+    /// Replace text in files of all repositories, skipping ignored files
+    Replace {
+        /// Workspace ID or name
+        /// If not provided, uses the currently entered workspace
+        #[arg(short, long)]
+        workspace: Option<String>,
+
+        /// Regular expression to search for
+        pattern: String,
+
+        /// Replacement text, can refer to capture groups like $1 or ${name}
+        replacement: String,
+
+        /// Treat pattern and replacement as literal strings
+        #[arg(short = 'F', long)]
+        fixed_strings: bool,
+
+        /// Only change files matching this glob, e.g. '*.yaml' (can be repeated)
+        #[arg(short, long)]
+        glob: Vec<String>,
+
+        /// Show a diff of the changes without writing them
+        #[arg(short = 'n', long)]
+        dry_run: bool,
+
+        /// Show the changes of each file and ask before writing them
+        #[arg(short, long, conflicts_with = "dry_run")]
+        interactive: bool,
+    },
+    /// Change values in a JSON, YAML or TOML file in every repository
+    Edit {
+        /// Workspace ID or name
+        /// If not provided, uses the currently entered workspace
+        #[arg(short, long)]
+        workspace: Option<String>,
+
+        /// File to edit, relative to each repository, e.g. package.json
+        #[arg(short, long)]
+        file: String,
+
+        /// Set a value, e.g. '.engines.node=">=20"'. Values that are not valid JSON are used as strings (can be repeated)
+        #[arg(
+            short,
+            long,
+            value_name = "PATH=VALUE",
+            required_unless_present = "delete"
+        )]
+        set: Vec<String>,
+
+        /// Remove a value, e.g. '.devDependencies.tslint' (can be repeated)
+        #[arg(short, long, value_name = "PATH")]
+        delete: Vec<String>,
+
+        /// Only report which repositories would change
+        #[arg(short = 'n', long)]
+        dry_run: bool,
+    },
+    /// Commit in every repository with changes, using the same message
+    Commit {
+        /// Workspace ID or name
+        /// If not provided, uses the currently entered workspace
+        #[arg(short, long)]
+        workspace: Option<String>,
+
+        /// Commit message. `{repo}` and `{branch}` are replaced in each repository
+        #[arg(short, long, conflicts_with = "message_file")]
+        message: Option<String>,
+
+        /// Read the commit message from a file, with the same replacements as --message
+        #[arg(short = 'F', long)]
+        message_file: Option<std::path::PathBuf>,
+
+        /// Stage all changes before committing, including untracked files
+        #[arg(short, long)]
+        all: bool,
+
+        /// Stage changes matching this pathspec before committing (can be repeated)
+        #[arg(short, long)]
+        pathspec: Vec<String>,
+    },
+    /// Push the current branch of every repository that is ahead of its upstream
+    Push {
+        /// Workspace ID or name
+        /// If not provided, uses the currently entered workspace
+        #[arg(short, long)]
+        workspace: Option<String>,
+
+        /// Also push repositories whose current branch is the remote default branch
+        #[arg(long)]
+        allow_default_branch: bool,
+
+        /// Overwrite remote branches, unless they changed since the last fetch
+        #[arg(long)]
+        force_with_lease: bool,
+    },
+    /// Build and test every repository with the command for its ecosystem
+    Check {
+        /// Workspace ID or name
+        /// If not provided, uses the currently entered workspace
+        #[arg(short, long)]
+        workspace: Option<String>,
+
+        /// Also write the results as JUnit XML to this file
+        #[arg(long, value_name = "PATH")]
+        junit: Option<std::path::PathBuf>,
+    },
+    /// Check that all repositories use the git config overrides
+    Doctor {
+        /// Workspace ID or name
+        /// If not provided, uses the currently entered workspace
+        #[arg(short, long)]
+        workspace: Option<String>,
+
+        /// Re-apply the overrides in repositories where they differ
+        #[arg(long)]
+        fix: bool,
+    },
+    /// List the dependency versions pinned in the lockfiles of all repositories
+    Deps {
+        /// Workspace ID or name
+        /// If not provided, uses the currently entered workspace
+        #[arg(short, long, global = true)]
+        workspace: Option<String>,
+
+        /// Only include dependencies of this ecosystem
+        #[arg(short, long, value_enum, global = true)]
+        ecosystem: Option<ecosystem::Ecosystem>,
+
+        /// Output format
+        #[arg(short, long, value_enum, default_value_t, global = true)]
+        format: deps::DepsFormat,
+
+        #[command(subcommand)]
+        command: Option<DepsCommands>,
+    },
+    /// Search all repositories, skipping ignored files
+    Grep {
+        /// Workspace ID or name
+        /// If not provided, uses the currently entered workspace
+        #[arg(short, long)]
+        workspace: Option<String>,
+
+        /// Regular expression to search for
+        pattern: String,
+
+        /// Only search paths matching these pathspecs
+        pathspec: Vec<String>,
+
+        /// Ignore case differences
+        #[arg(short, long)]
+        ignore_case: bool,
+
+        /// Treat the pattern as a literal string instead of a regular expression
+        #[arg(short = 'F', long)]
+        fixed_strings: bool,
+
+        /// Only print the paths of matching files
+        #[arg(short = 'l', long)]
+        files_with_matches: bool,
+
+        /// Output format
+        #[arg(short, long, value_enum, default_value_t)]
+        format: OutputFormat,
+    },
+    /// Show a combined diff of all repositories
+    Diff {
+        /// Workspace ID or name
+        /// If not provided, uses the currently entered workspace
+        #[arg(short, long)]
+        workspace: Option<String>,
+
+        /// Show staged instead of unstaged changes
+        #[arg(long, alias = "cached")]
+        staged: bool,
+
+        /// Only show a summary of changed files
+        #[arg(long)]
+        stat: bool,
+
+        /// Compare with the merge base of REF and HEAD.
+        /// Use `default-branch` for the remote default branch of each repository
+        #[arg(long, value_name = "REF")]
+        against: Option<String>,
+
+        /// Show repositories with identical changes together
+        #[arg(short, long)]
+        group: bool,
+
+        /// Do not pipe the output into a pager
+        #[arg(long)]
+        no_pager: bool,
+    },
+    /// Browse the repositories of a workspace in an interactive terminal UI
+    Ui {
+        /// Workspace ID or name
         /// If not provided, uses the currently entered workspace
         #[arg(short, long)]
         workspace: Option<String>,
     },
     /// Run a command in each repository
     Apply {
-        /// Workspace ID
+        /// Workspace ID or name
         /// If not provided, uses the currently entered workspace
         #[arg(short, long)]
         workspace: Option<String>,
@@ -62,13 +359,58 @@ enum Commands {
         #[arg(short, long)]
         script: Option<clap::builder::OsStr>,
 
+        /// Name of a script in the recipes directory to run
+        #[arg(short, long, conflicts_with = "script")]
+        recipe: Option<String>,
+
+        /// Shell command line to run, using $SHELL or the configured shell
+        #[arg(long, value_name = "COMMAND", conflicts_with_all = ["script", "recipe", "command"])]
+        shell: Option<String>,
+
+        /// Re-run the command of a previous run, only in the repositories where it failed.
+        /// Uses the most recent run if no ID is given
+        #[arg(long, num_args = 0..=1, value_name = "RUN_ID", conflicts_with_all = ["script", "recipe", "shell", "command"])]
+        rerun_failed: Option<Option<String>>,
+
+        /// Only run in repositories where this shell command succeeds, others are skipped.
+        /// Can be repeated
+        #[arg(long = "if", value_name = "COMMAND")]
+        if_command: Vec<String>,
+
+        /// Only run in repositories containing this file, others are skipped.
+        /// Can be repeated
+        #[arg(long, value_name = "PATH")]
+        if_file: Vec<std::path::PathBuf>,
+
+        /// Only run in repositories of these ecosystems, detected from their manifest files
+        #[arg(long, value_enum, value_delimiter = ',')]
+        ecosystem: Vec<ecosystem::Ecosystem>,
+
+        /// Stop the command in a repository after this time, e.g. 90s, 10m or 1h
+        #[arg(long, value_name = "DURATION", value_parser = apply::parse_duration)]
+        timeout: Option<std::time::Duration>,
+
+        /// Ask before running in each repository
+        #[arg(long, conflicts_with = "confirm_after_diff")]
+        confirm: bool,
+
+        /// Show the changes made in each repository and ask whether to keep or revert them.
+        /// Repositories with uncommitted changes are skipped
+        #[arg(long)]
+        confirm_after_diff: bool,
+
+        /// Write the output of each repository only to a log file, and show one progress line
+        /// per repository. Logs go to the run directory in .nut/runs unless a directory is given
+        #[arg(long, num_args = 0..=1, value_name = "DIR")]
+        log_dir: Option<Option<std::path::PathBuf>>,
+
         /// Command and arguments to run (must come after --)
         #[arg(trailing_var_arg = true, required = false)]
         command: Vec<clap::builder::OsStr>,
     },
     /// Import repositories into a workspace
     Import {
-        /// Workspace ID
+        /// Workspace ID or name
         /// If not provided, uses the currently entered workspace
         #[arg(short, long)]
         workspace: Option<String>,
@@ -91,13 +433,75 @@ enum Commands {
         #[arg(trailing_var_arg = true, required = false)]
         full_repository_names: Vec<String>,
     },
+    /// Export a workspace, including unpushed commits and uncommitted changes
+    Export {
+        /// Workspace ID or name
+        /// If not provided, uses the currently entered workspace
+        id: Option<String>,
+
+        /// Path of the bundle to write, e.g. campaign.tar.zst
+        #[arg(short, long)]
+        output: std::path::PathBuf,
+    },
+    /// Create a new workspace from a bundle created with `nut export`
+    ImportBundle {
+        /// Path of the bundle
+        bundle: std::path::PathBuf,
+    },
+    /// Show the log of workspace-wide operations
+    Log {
+        /// Workspace ID or name
+        /// If not provided, uses the currently entered workspace
+        #[arg(short, long)]
+        workspace: Option<String>,
+
+        /// Only show the last N operations
+        #[arg(short, long)]
+        limit: Option<usize>,
+
+        /// Output format
+        #[arg(short, long, value_enum, default_value_t)]
+        format: OutputFormat,
+    },
+    /// Revert the branch and commit changes of the last logged operation
+    Undo {
+        /// Workspace ID or name
+        /// If not provided, uses the currently entered workspace
+        #[arg(short, long)]
+        workspace: Option<String>,
+    },
+    /// Save and restore the state of all repositories
+    Snapshot {
+        /// Workspace ID or name
+        /// If not provided, uses the currently entered workspace
+        #[arg(short, long, global = true)]
+        workspace: Option<String>,
+
+        #[command(subcommand)]
+        command: SnapshotCommands,
+    },
+    /// Show the results of previous apply runs
+    Runs {
+        /// Workspace ID or name
+        /// If not provided, uses the currently entered workspace
+        #[arg(short, long, global = true)]
+        workspace: Option<String>,
+
+        #[command(subcommand)]
+        command: RunsCommands,
+    },
+    /// Manage the named scripts used by 'nut apply --recipe'
+    Recipes {
+        #[command(subcommand)]
+        command: RecipesCommands,
+    },
     /// Print git cache directory
     CacheDir {},
     /// Print data directory containing workspaces
     DataDir {},
     /// Print workspace directory
     WorkspaceDir {
-        /// Workspace ID
+        /// Workspace ID or name
         /// If not provided, uses the currently entered workspace
         #[arg(short, long)]
         workspace: Option<String>,
@@ -107,25 +511,102 @@ enum Commands {
         /// Set the workspace directory
         #[arg(short, long)]
         workspace_dir: Option<String>,
+
+        /// Set the directory containing recipes for 'nut apply --recipe'
+        #[arg(long)]
+        recipes_dir: Option<String>,
+
+        /// Set the shell for 'nut apply --shell', instead of $SHELL
+        #[arg(long)]
+        shell: Option<String>,
+
+        /// Set the command 'nut check' runs for an ecosystem, as ECOSYSTEM=COMMAND.
+        /// An empty command restores the default
+        #[arg(long, value_name = "ECOSYSTEM=COMMAND")]
+        check_command: Vec<String>,
+
+        /// Set the command run by a hook (post-import, pre-apply, post-apply or enter),
+        /// as HOOK=COMMAND. An empty command removes it
+        #[arg(long, value_name = "HOOK=COMMAND")]
+        hook: Vec<String>,
+
+        /// Set a git config value for every cloned repository, as KEY=VALUE.
+        /// An empty value removes it
+        #[arg(long, value_name = "KEY=VALUE")]
+        git_config: Vec<String>,
+
+        /// Workspace ID or name to set --git-config for, instead of all workspaces
+        #[arg(long, requires = "git_config")]
+        workspace: Option<String>,
     },
 }
 
-/// Process a repository: fetch commit info and clone
+#[derive(Subcommand)]
+enum SnapshotCommands {
+    /// Record HEAD, branch, index and working tree of every repository
+    Create {
+        /// Snapshot name, defaults to the current time
+        name: Option<String>,
+    },
+    /// Roll all repositories back to a snapshot
+    Restore {
+        /// Snapshot name
+        name: String,
+    },
+    /// List snapshots
+    List {},
+    /// Delete a snapshot
+    Delete {
+        /// Snapshot name
+        name: String,
+    },
+}
+
+#[derive(Subcommand)]
+enum RunsCommands {
+    /// List previous runs
+    List {},
+    /// Show the results of a run, or the output captured in one repository
+    Show {
+        /// Run ID
+        run_id: String,
+
+        /// Repository path, relative to the workspace
+        repo: Option<String>,
+    },
+}
+
+#[derive(Subcommand)]
+enum DepsCommands {
+    /// Show which repositories use a dependency
+    Why {
+        /// Dependency name, optionally with a version requirement like 'lodash<4.17.21'
+        query: String,
+    },
+}
+
+#[derive(Subcommand)]
+enum RecipesCommands {
+    /// List the recipes with their description and requirements
+    List {},
+}
+
+/// Process a repository: fetch commit info and clone. Returns the full repository name.
 async fn process_repo(
-    workspace_path: &std::path::Path,
+    workspace: &Workspace,
     crab: &octocrab::Octocrab,
     details: octocrab::models::Repository,
     dry_run: bool,
-) -> Result<()> {
+) -> Result<String> {
     let repo = crab.repos(
         details.owner.ok_or(NutError::InvalidUtf8)?.login,
         details.name,
     );
-    let full_name = &details.full_name.ok_or(NutError::InvalidUtf8)?;
+    let full_name = details.full_name.ok_or(NutError::InvalidUtf8)?;
     println!("{}", full_name);
 
     if dry_run {
-        return Ok(());
+        return Ok(full_name);
     }
 
     let default_branch = &details.default_branch;
@@ -141,8 +622,14 @@ async fn process_repo(
             .map(|c| c.sha.clone()),
         None => None,
     };
-    git::clone(workspace_path, full_name, &latest_commit, default_branch).await?;
-    Ok(())
+    let is_new = !workspace.path.join(&full_name).exists();
+    git::clone(&workspace.path, &full_name, &latest_commit, default_branch).await?;
+    if is_new {
+        let overrides = gitconfig::overrides(workspace).await?;
+        gitconfig::apply(&workspace.path.join(&full_name), &overrides).await?;
+        hooks::post_import(workspace, std::path::Path::new(&full_name)).await?;
+    }
+    Ok(full_name)
 }
 
 #[tokio::main(flavor = "multi_thread")]
@@ -168,187 +655,345 @@ async fn main() -> Result<()> {
     // You can check for the existence of subcommands, and if found use their
     // matches just as you would the top level cmd
     match &cli.command {
-        Some(Commands::Create { description }) => {
+        Some(Commands::Create { description, name }) => {
             if enter::get_entered_workspace().await.is_ok() {
                 return Err(NutError::AlreadyInWorkspace.into());
             }
 
-            let data_local_dir = dirs::get_data_local_dir().await?;
-
-            let ulid = ulid::Ulid::new();
+            if let Some(name) = name {
+                Workspace::validate_name(name, None).await?;
+            }
 
-            let workspace_path = data_local_dir.join(ulid.to_string()).join(".nut");
-            tokio::fs::create_dir_all(&workspace_path)
-                .await
-                .map_err(|e| NutError::CreateDirectoryFailed {
-                    path: workspace_path.clone(),
-                    source: e,
-                })?;
+            let workspace = Workspace::create(description).await?;
 
-            // write description file
-            let desc_path = data_local_dir
-                .join(ulid.to_string())
-                .join(".nut/description");
-            tokio::fs::write(&desc_path, description)
-                .await
-                .map_err(|e| NutError::WriteFileFailed {
-                    path: desc_path,
-                    source: e,
-                })?;
+            if let Some(name) = name {
+                workspace.set_name(name).await?;
+            }
 
-            enter::enter(ulid).await?;
+            enter::enter(&workspace).await?;
         }
         Some(Commands::Enter { id }) => {
             if enter::get_entered_workspace().await.is_ok() {
                 return Err(NutError::AlreadyInWorkspace.into());
             }
 
-            let ulid = id.parse().map_err(|e| NutError::InvalidWorkspaceId {
-                id: id.clone(),
-                source: e,
-            })?;
-            enter::enter(ulid).await?;
+            let workspace = Workspace::find(id).await?;
+            enter::enter(&workspace).await?;
         }
-        Some(Commands::List {}) => {
-            let data_local_dir = dirs::get_data_local_dir().await?;
-            let mut entries = tokio::fs::read_dir(&data_local_dir).await.map_err(|e| {
-                NutError::ReadDirectoryFailed {
-                    path: data_local_dir.clone(),
-                    source: e,
-                }
-            })?;
-
-            // Collect all workspaces with their metadata
-            let mut workspaces: Vec<(Ulid, DateTime<Utc>, String)> = Vec::new();
-
-            while let Some(entry) = entries.next_entry().await.into_diagnostic()? {
-                if entry.file_type().await.into_diagnostic()?.is_dir() {
-                    let ulid_str = entry
-                        .file_name()
-                        .into_string()
-                        .map_err(|_| NutError::InvalidUtf8)?;
-                    if let Ok(ulid) = Ulid::from_string(&ulid_str) {
-                        let datetime: DateTime<Utc> = ulid.datetime().into();
-                        let desc_path = entry.path().join(".nut/description");
-                        let description = tokio::fs::read_to_string(&desc_path)
-                            .await
-                            .unwrap_or("(missing description)".to_string());
-                        workspaces.push((ulid, datetime, description));
+        Some(Commands::Rename { id, name }) => {
+            let workspace = Workspace::find(id).await?;
+            workspace.set_name(name).await?;
+            println!("Workspace {} is now named {}", workspace.id, name);
+        }
+        Some(Commands::Describe { id, description }) => {
+            let workspace = Workspace::find(id).await?;
+            workspace.set_description(description).await?;
+        }
+        Some(Commands::Tag { id, tags, remove }) => {
+            let workspace = Workspace::find(id).await?;
+            let mut current = workspace.tags().await;
+            if *remove {
+                current.retain(|t| !tags.contains(t));
+            } else {
+                for tag in tags {
+                    if !current.contains(tag) {
+                        current.push(tag.clone());
                     }
                 }
             }
-
-            // Sort by timestamp, most recent first
-            workspaces.sort_by(|a, b| b.1.cmp(&a.1));
-
-            // Display workspaces
-            for (ulid, datetime, description) in workspaces {
-                println!("{}", ulid.to_string());
-                println!("  Created: {}", datetime.format("%Y-%m-%d %H:%M:%S"));
-                println!("  {}", description);
-                println!();
-            }
+            workspace.set_tags(&current).await?;
         }
-        Some(Commands::Status { workspace }) => {
+        Some(Commands::Archive { id }) => {
+            Workspace::find(id).await?.set_archived(true).await?;
+        }
+        Some(Commands::Unarchive { id }) => {
+            Workspace::find(id).await?.set_archived(false).await?;
+        }
+        Some(Commands::List {
+            archived,
+            tag,
+            columns,
+            sort,
+            limit,
+            format,
+        }) => {
+            list::list(list::ListOptions {
+                archived: *archived,
+                tags: tag,
+                columns,
+                sort: *sort,
+                limit: *limit,
+                format: *format,
+            })
+            .await?;
+        }
+        Some(Commands::Status {
+            workspace,
+            long,
+            format,
+        }) => {
             let workspace = Workspace::resolve(workspace).await?;
-            let statuses = git::get_all_repos_status(&workspace.path).await?;
-
-            // Count repositories with and without changes
-            let repos_with_changes: Vec<_> = statuses.iter().filter(|s| s.has_changes).collect();
-            let total_repos = statuses.len();
-            let clean_repos = total_repos - repos_with_changes.len();
-
-            // Print summary
-            println!("Workspace status:");
-            println!("  {} repositories total", total_repos);
-            println!(
-                "  {} clean, {} with changes",
-                clean_repos,
-                repos_with_changes.len()
-            );
-            println!();
-
-            // Print details for repos with changes
-            if repos_with_changes.is_empty() {
-                println!("All repositories are clean.");
-            } else {
-                println!("Repositories with changes:");
-                println!();
-
-                for status in repos_with_changes {
-                    println!(
-                        "  {} ({})",
-                        status.path_relative.to_string_lossy(),
-                        status.current_branch
-                    );
-
-                    if status.staged_files > 0 {
-                        println!("    {} file(s) with staged changes", status.staged_files);
-                    }
-                    if status.modified_files > 0 {
-                        println!(
-                            "    {} file(s) with unstaged changes",
-                            status.modified_files
-                        );
-                    }
-                    if status.untracked_files > 0 {
-                        println!("    {} untracked file(s)", status.untracked_files);
-                    }
-                    println!();
+            let options = status::StatusOptions {
+                long: *long,
+                format: *format,
+            };
+            status::status(&workspace, &options).await?;
+        }
+        Some(Commands::Replace {
+            workspace,
+            pattern,
+            replacement,
+            fixed_strings,
+            glob,
+            dry_run,
+            interactive,
+        }) => {
+            let workspace = Workspace::resolve(workspace).await?;
+            let options = replace::ReplaceOptions {
+                pattern: pattern.clone(),
+                replacement: replacement.clone(),
+                fixed_strings: *fixed_strings,
+                globs: glob.clone(),
+                dry_run: *dry_run,
+                interactive: *interactive,
+            };
+            replace::replace(&workspace, &options).await?;
+        }
+        Some(Commands::Edit {
+            workspace,
+            file,
+            set,
+            delete,
+            dry_run,
+        }) => {
+            let workspace = Workspace::resolve(workspace).await?;
+            let options = edit::EditOptions {
+                file: file.clone(),
+                set: set.clone(),
+                delete: delete.clone(),
+                dry_run: *dry_run,
+            };
+            edit::edit(&workspace, &options).await?;
+        }
+        Some(Commands::Commit {
+            workspace,
+            message,
+            message_file,
+            all,
+            pathspec,
+        }) => {
+            let workspace = Workspace::resolve(workspace).await?;
+            let options = commit::CommitOptions {
+                template: commit::read_template(message, message_file).await?,
+                all: *all,
+                pathspec: pathspec.clone(),
+            };
+            commit::commit(&workspace, &options).await?;
+        }
+        Some(Commands::Push {
+            workspace,
+            allow_default_branch,
+            force_with_lease,
+        }) => {
+            let workspace = Workspace::resolve(workspace).await?;
+            let options = push::PushOptions {
+                allow_default_branch: *allow_default_branch,
+                force_with_lease: *force_with_lease,
+            };
+            push::push(&workspace, &options).await?;
+        }
+        Some(Commands::Check { workspace, junit }) => {
+            let workspace = Workspace::resolve(workspace).await?;
+            let options = check::CheckOptions {
+                junit: junit.clone(),
+            };
+            check::check(&workspace, &options).await?;
+        }
+        Some(Commands::Doctor { workspace, fix }) => {
+            let workspace = Workspace::resolve(workspace).await?;
+            let options = doctor::DoctorOptions { fix: *fix };
+            doctor::doctor(&workspace, &options).await?;
+        }
+        Some(Commands::Deps {
+            workspace,
+            ecosystem,
+            format,
+            command,
+        }) => {
+            let workspace = Workspace::resolve(workspace).await?;
+            match command {
+                None => deps::inventory(&workspace, *ecosystem, *format).await?,
+                Some(DepsCommands::Why { query }) => {
+                    deps::why(&workspace, query, *ecosystem, *format).await?
                 }
             }
         }
+        Some(Commands::Grep {
+            workspace,
+            pattern,
+            pathspec,
+            ignore_case,
+            fixed_strings,
+            files_with_matches,
+            format,
+        }) => {
+            let workspace = Workspace::resolve(workspace).await?;
+            let options = grep::GrepOptions {
+                pattern: pattern.clone(),
+                ignore_case: *ignore_case,
+                fixed_strings: *fixed_strings,
+                files_with_matches: *files_with_matches,
+                pathspec: pathspec.clone(),
+                format: *format,
+            };
+            grep::grep(&workspace, &options).await?;
+        }
+        Some(Commands::Diff {
+            workspace,
+            staged,
+            stat,
+            against,
+            group,
+            no_pager,
+        }) => {
+            let workspace = Workspace::resolve(workspace).await?;
+            let options = diff::DiffOptions {
+                staged: *staged,
+                stat: *stat,
+                against: against.clone(),
+                group: *group,
+                pager: !no_pager,
+            };
+            diff::diff(&workspace, &options).await?;
+        }
+        Some(Commands::Ui { workspace }) => {
+            let workspace = Workspace::resolve(workspace).await?;
+            ui::run(&workspace).await?;
+        }
         Some(Commands::Apply {
             workspace,
             script,
+            recipe,
+            shell,
+            rerun_failed,
+            if_command,
+            if_file,
+            ecosystem,
+            timeout,
+            confirm,
+            confirm_after_diff,
+            log_dir,
             command,
         }) => {
             let workspace = Workspace::resolve(workspace).await?;
 
-            // Handle script mode
-            if let Some(script_path) = script {
-                let absolute_script_path =
-                    tokio::fs::canonicalize(script_path).await.map_err(|e| {
-                        NutError::ScriptPathInvalid {
-                            path: script_path.display().to_string(),
-                            source: e,
-                        }
-                    })?;
+            let mut options = apply::ApplyOptions {
+                command: Vec::new(),
+                repos: None,
+                rerun_of: None,
+                conditions: if_command
+                    .iter()
+                    .cloned()
+                    .map(apply::Condition::Command)
+                    .chain(if_file.iter().cloned().map(apply::Condition::File))
+                    .chain(
+                        (!ecosystem.is_empty())
+                            .then(|| apply::Condition::Ecosystem(ecosystem.clone())),
+                    )
+                    .collect(),
+                recipe: None,
+                requires: Vec::new(),
+                timeout: *timeout,
+                confirm: if *confirm {
+                    Some(apply::Confirm::BeforeRun)
+                } else if *confirm_after_diff {
+                    Some(apply::Confirm::AfterDiff)
+                } else {
+                    None
+                },
+                log_only: log_dir.is_some(),
+                log_dir: log_dir
+                    .clone()
+                    .flatten()
+                    .map(std::path::absolute)
+                    .transpose()
+                    .map_err(|e| NutError::GetCurrentDirectoryFailed { source: e })?,
+            };
 
-                // only for unix
-                #[cfg(unix)]
-                {
-                    use std::os::unix::fs::PermissionsExt;
-                    let metadata =
-                        tokio::fs::metadata(&absolute_script_path)
-                            .await
-                            .map_err(|e| NutError::ScriptPathInvalid {
-                                path: script_path.display().to_string(),
-                                source: e,
-                            })?;
-                    let permissions = metadata.permissions();
-                    if (permissions.mode() & 0o111) == 0 {
-                        return Err(NutError::ScriptNotExecutable {
-                            path: script_path.display().to_string(),
-                        }
-                        .into());
+            if let Some(run_id) = rerun_failed {
+                let previous = runs::Run::load(&workspace.path, run_id.as_deref())?;
+                let failed = previous.failed_repos();
+                if failed.is_empty() {
+                    println!("No failed repositories in run {}", previous.id);
+                    return Ok(());
+                }
+                let missing = recipe::missing_tools(&previous.requires);
+                if !missing.is_empty() {
+                    return Err(NutError::RecipeToolsMissing {
+                        recipe: previous.recipe.unwrap_or_default(),
+                        tools: missing.join(", "),
                     }
+                    .into());
                 }
-
-                let mut args: Vec<&OsStr> = vec![absolute_script_path.as_os_str()];
-                args.extend(command.iter().map(|s| s.as_os_str()));
-                git::apply_command(&workspace.path, args).await?;
-            } else {
-                // Direct command mode
-                if command.is_empty() {
-                    return Err(NutError::ApplyMissingCommand.into());
+                options.command = previous.command.iter().map(OsString::from).collect();
+                options.repos = Some(failed);
+                options.rerun_of = Some(previous.id);
+                // the conditions of the original run still decide which repositories are skipped
+                options.conditions.splice(0..0, previous.conditions);
+                options.recipe = previous.recipe;
+                options.requires = previous.requires;
+            } else if let Some(script_path) = script {
+                let script_path = apply::resolve_script(std::path::Path::new(script_path)).await?;
+                options.command.push(script_path.into_os_string());
+            } else if let Some(shell_command) = shell {
+                options.command = apply::shell_command(shell_command)?;
+            } else if let Some(name) = recipe {
+                let recipe = recipe::Recipe::find(name)?;
+                let missing = recipe.missing_tools();
+                if !missing.is_empty() {
+                    return Err(NutError::RecipeToolsMissing {
+                        recipe: recipe.name,
+                        tools: missing.join(", "),
+                    }
+                    .into());
                 }
+                let script_path = apply::resolve_script(&recipe.path).await?;
+                options.command.push(script_path.into_os_string());
+                options
+                    .conditions
+                    .extend(recipe.applies_if.map(apply::Condition::Command));
+                options.recipe = Some(recipe.name);
+                options.requires = recipe.requires;
+            } else if command.is_empty() {
+                // Direct command mode
+                return Err(NutError::ApplyMissingCommand.into());
+            }
+            options
+                .command
+                .extend(command.iter().map(|s| s.as_os_str().to_os_string()));
 
-                git::apply_command(
-                    &workspace.path,
-                    command.iter().map(|s| s.as_os_str()).collect(),
-                )
-                .await?;
+            let mut recorder = oplog::Recorder::start(&workspace, "apply").await?;
+            let run = apply::apply(&workspace, options).await;
+            for repo in run.iter().flat_map(|r| &r.repos) {
+                let result = repo.message.as_deref().unwrap_or("ok");
+                recorder.set_result(std::path::Path::new(&repo.repo), result);
+            }
+            recorder.finish().await?;
+            let run = run?;
+            if run.aborted {
+                return Err(NutError::ApplyAborted { id: run.id }.into());
+            }
+            // after the run is recorded, so a failing hook does not hide its result
+            if !run.repos.is_empty() {
+                let status = match run.failed_repos().is_empty() {
+                    true => "success",
+                    false => "failed",
+                };
+                let env = [
+                    ("NUT_RUN_ID", OsString::from(&run.id)),
+                    ("NUT_RUN_STATUS", OsString::from(status)),
+                ];
+                hooks::run(hooks::Hook::PostApply, &workspace, &workspace.path, &env).await?;
             }
         }
         Some(Commands::Import {
@@ -374,47 +1019,122 @@ async fn main() -> Result<()> {
                 .user_access_token(token.into_boxed_str())
                 .into_diagnostic()?;
 
-            if let Some(q) = query {
-                // Use search API with query
-                let mut page = crab
-                    .search()
-                    .repositories(q)
-                    .send()
-                    .await
-                    .into_diagnostic()?;
-
-                loop {
-                    for details in page.items {
-                        process_repo(&workspace.path, &crab, details, *dry_run).await?;
-                    }
+            let mut recorder = match dry_run {
+                true => None,
+                false => Some(oplog::Recorder::start(&workspace, "import").await?),
+            };
+            let mut imported = |full_name: String| {
+                if let Some(recorder) = recorder.as_mut() {
+                    recorder.set_result(std::path::Path::new(&full_name), "imported");
+                }
+            };
 
-                    page = match crab
-                        .get_page::<octocrab::models::Repository>(&page.next)
+            let result: Result<()> = async {
+                if let Some(q) = query {
+                    // Use search API with query
+                    let mut page = crab
+                        .search()
+                        .repositories(q)
+                        .send()
                         .await
-                        .into_diagnostic()?
-                    {
-                        Some(next_page) => next_page,
-                        None => break,
+                        .into_diagnostic()?;
+
+                    loop {
+                        for details in page.items {
+                            imported(process_repo(&workspace, &crab, details, *dry_run).await?);
+                        }
+
+                        page = match crab
+                            .get_page::<octocrab::models::Repository>(&page.next)
+                            .await
+                            .into_diagnostic()?
+                        {
+                            Some(next_page) => next_page,
+                            None => break,
+                        }
                     }
-                }
-            } else {
-                // Import specific repositories by full name
-                for full_name in full_repository_names {
-                    let parts: Vec<&str> = full_name.split('/').collect();
-                    if parts.len() != 2 {
-                        return Err(NutError::InvalidRepositoryName {
-                            name: full_name.clone(),
+                } else {
+                    // Import specific repositories by full name
+                    for full_name in full_repository_names {
+                        let parts: Vec<&str> = full_name.split('/').collect();
+                        if parts.len() != 2 {
+                            return Err(NutError::InvalidRepositoryName {
+                                name: full_name.clone(),
+                            }
+                            .into());
                         }
-                        .into());
+                        let owner = parts[0];
+                        let repo = parts[1];
+                        let repo_handler = crab.repos(owner, repo);
+                        let details = repo_handler.get().await.into_diagnostic()?;
+                        imported(process_repo(&workspace, &crab, details, *dry_run).await?);
                     }
-                    let owner = parts[0];
-                    let repo = parts[1];
-                    let repo_handler = crab.repos(owner, repo);
-                    let details = repo_handler.get().await.into_diagnostic()?;
-                    process_repo(&workspace.path, &crab, details, *dry_run).await?;
                 }
+                Ok(())
             }
+            .await;
+
+            if let Some(recorder) = recorder {
+                recorder.finish().await?;
+            }
+            result?;
+        }
+        Some(Commands::Export { id, output }) => {
+            let workspace = Workspace::resolve(id).await?;
+            bundle::export(&workspace, output).await?;
+            println!(
+                "Exported workspace {} to {}",
+                workspace.id,
+                output.display()
+            );
+        }
+        Some(Commands::ImportBundle { bundle }) => {
+            let workspace = bundle::import(bundle).await?;
+            println!("Imported workspace {}", workspace.id);
+        }
+        Some(Commands::Log {
+            workspace,
+            limit,
+            format,
+        }) => {
+            let workspace = Workspace::resolve(workspace).await?;
+            oplog::show(&workspace, *limit, *format)?;
         }
+        Some(Commands::Undo { workspace }) => {
+            let workspace = Workspace::resolve(workspace).await?;
+            oplog::undo(&workspace).await?;
+        }
+        Some(Commands::Snapshot { workspace, command }) => {
+            let workspace = Workspace::resolve(workspace).await?;
+            match command {
+                SnapshotCommands::Create { name } => {
+                    let name = name
+                        .clone()
+                        .unwrap_or_else(|| Utc::now().format("%Y%m%d-%H%M%S").to_string());
+                    snapshot::create(&workspace, &name).await?;
+                }
+                SnapshotCommands::Restore { name } => {
+                    let recorder = oplog::Recorder::start(&workspace, "snapshot restore").await?;
+                    let result = snapshot::restore(&workspace, name).await;
+                    recorder.finish().await?;
+                    result?;
+                }
+                SnapshotCommands::List {} => snapshot::list(&workspace).await?,
+                SnapshotCommands::Delete { name } => snapshot::delete(&workspace, name).await?,
+            }
+        }
+        Some(Commands::Runs { workspace, command }) => {
+            let workspace = Workspace::resolve(workspace).await?;
+            match command {
+                RunsCommands::List {} => runs::list(&workspace)?,
+                RunsCommands::Show { run_id, repo } => {
+                    runs::show(&workspace, run_id, repo.as_deref())?
+                }
+            }
+        }
+        Some(Commands::Recipes { command }) => match command {
+            RecipesCommands::List {} => recipe::list()?,
+        },
         Some(Commands::CacheDir {}) => {
             write_path_to_stdout(get_cache_dir().await?)?;
         }
@@ -425,7 +1145,15 @@ async fn main() -> Result<()> {
             let workspace = Workspace::resolve(workspace).await?;
             write_path_to_stdout(workspace.path.clone())?;
         }
-        Some(Commands::Config { workspace_dir }) => {
+        Some(Commands::Config {
+            workspace_dir,
+            recipes_dir,
+            shell,
+            check_command,
+            hook,
+            git_config,
+            workspace,
+        }) => {
             let mut config = config::NutConfig::load()?;
 
             if let Some(dir) = workspace_dir {
@@ -434,6 +1162,98 @@ async fn main() -> Result<()> {
                 println!("Workspace directory set to: {}", path.display());
             }
 
+            if let Some(dir) = recipes_dir {
+                let path = std::path::PathBuf::from(dir);
+                config.recipes_dir = Some(path.clone());
+                println!("Recipes directory set to: {}", path.display());
+            }
+
+            if let Some(shell) = shell {
+                let path = std::path::PathBuf::from(shell);
+                config.shell = Some(path.clone());
+                println!("Shell set to: {}", path.display());
+            }
+
+            for value in check_command {
+                let (name, command) =
+                    value
+                        .split_once('=')
+                        .ok_or_else(|| NutError::InvalidCheckCommand {
+                            value: value.clone(),
+                        })?;
+                let ecosystem = ecosystem::Ecosystem::from_name(name).ok_or_else(|| {
+                    NutError::UnknownEcosystem {
+                        name: name.to_string(),
+                    }
+                })?;
+                if command.is_empty() {
+                    config.check_commands.remove(ecosystem.name());
+                    println!(
+                        "Check command for {ecosystem} reset to: {}",
+                        ecosystem.default_check_command()
+                    );
+                } else {
+                    config
+                        .check_commands
+                        .insert(ecosystem.name().to_string(), command.to_string());
+                    println!("Check command for {ecosystem} set to: {command}");
+                }
+            }
+
+            for value in hook {
+                let (name, command) =
+                    value.split_once('=').ok_or_else(|| NutError::InvalidHook {
+                        value: value.clone(),
+                    })?;
+                let hook = hooks::Hook::from_name(name).ok_or_else(|| NutError::UnknownHook {
+                    name: name.to_string(),
+                })?;
+                if command.is_empty() {
+                    config.hooks.remove(hook.name());
+                    println!("Hook {hook} removed");
+                } else {
+                    config
+                        .hooks
+                        .insert(hook.name().to_string(), command.to_string());
+                    println!("Hook {hook} set to: {command}");
+                }
+            }
+
+            let workspace = match workspace {
+                Some(_) => Some(Workspace::resolve(workspace).await?),
+                None => None,
+            };
+            for value in git_config {
+                let (key, value) = value
+                    .split_once('=')
+                    .filter(|(key, _)| key.contains('.'))
+                    .ok_or_else(|| NutError::InvalidGitConfig {
+                        value: value.clone(),
+                    })?;
+                let key = gitconfig::canonical_key(key);
+                let value = Some(value).filter(|v| !v.is_empty());
+                let scope = match &workspace {
+                    Some(workspace) => {
+                        gitconfig::set_workspace_override(workspace, &key, value).await?;
+                        format!("workspace {}", workspace.id)
+                    }
+                    None => {
+                        match value {
+                            Some(value) => config.git_config.insert(key.clone(), value.to_string()),
+                            None => config.git_config.remove(&key),
+                        };
+                        "all workspaces".to_string()
+                    }
+                };
+                match value {
+                    Some(value) => println!("Git config {key} set to {value} for {scope}"),
+                    None => println!("Git config {key} removed for {scope}"),
+                }
+            }
+            if workspace.is_some() && !git_config.is_empty() {
+                println!("Apply it to the existing repositories with: nut doctor --fix");
+            }
+
             config.save()?;
         }
         None => {}
@@ -0,0 +1,289 @@
+use std::collections::{BTreeMap, HashSet};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use ulid::Ulid;
+
+use crate::error::{NutError, Result};
+use crate::git::{self, GitCommand, HeadState};
+use crate::output::{OutputFormat, print_json};
+use crate::workspace::Workspace;
+
+const LOG_FILE: &str = "log.jsonl";
+
+/// One workspace-wide command, as recorded in `.nut/log.jsonl`
+#[derive(Serialize, Deserialize)]
+pub struct Operation {
+    pub id: String,
+    pub timestamp: DateTime<Utc>,
+    pub command: String,
+    pub args: Vec<String>,
+    pub repos: Vec<RepoEntry>,
+    /// ID of the operation reverted by this one
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub undoes: Option<String>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct RepoEntry {
+    pub repo: String,
+    pub result: String,
+    pub before: HeadState,
+    pub after: HeadState,
+}
+
+/// Records the HEAD of every repository before and after an operation
+pub struct Recorder {
+    workspace_path: PathBuf,
+    command: String,
+    args: Vec<String>,
+    before: BTreeMap<String, HeadState>,
+    results: BTreeMap<String, String>,
+}
+
+impl Recorder {
+    /// Start recording, using the arguments nut was called with
+    pub async fn start(workspace: &Workspace, command: &str) -> Result<Self> {
+        Ok(Self {
+            workspace_path: workspace.path.clone(),
+            command: command.to_string(),
+            args: invocation_args(),
+            before: head_states(&workspace.path).await?,
+            results: BTreeMap::new(),
+        })
+    }
+
+    pub fn set_result(&mut self, repo: &Path, result: impl Into<String>) {
+        self.results
+            .insert(repo.to_string_lossy().to_string(), result.into());
+    }
+
+    /// Append the operation to the log. Repositories without a result are only
+    /// recorded if their HEAD changed.
+    pub async fn finish(mut self) -> Result<()> {
+        let after = head_states(&self.workspace_path).await?;
+
+        let mut repos = Vec::new();
+        for (repo, after) in after {
+            let before = self.before.remove(&repo).unwrap_or_default();
+            let result = match self.results.remove(&repo) {
+                Some(result) => result,
+                None if before != after => "changed".to_string(),
+                None => continue,
+            };
+            repos.push(RepoEntry {
+                repo,
+                result,
+                before,
+                after,
+            });
+        }
+
+        append(
+            &self.workspace_path,
+            &Operation {
+                id: Ulid::new().to_string(),
+                timestamp: Utc::now(),
+                command: self.command,
+                args: self.args,
+                repos,
+                undoes: None,
+            },
+        )
+    }
+}
+
+pub fn read(workspace_path: &Path) -> Result<Vec<Operation>> {
+    let path = log_path(workspace_path);
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+    let contents = std::fs::read_to_string(&path).map_err(|e| NutError::ReadFileFailed {
+        path: path.clone(),
+        source: e,
+    })?;
+    contents
+        .lines()
+        .filter(|l| !l.trim().is_empty())
+        .map(|l| {
+            serde_json::from_str(l).map_err(|e| NutError::InvalidOperationLog {
+                path: path.clone(),
+                reason: e.to_string(),
+            })
+        })
+        .collect()
+}
+
+pub fn show(workspace: &Workspace, limit: Option<usize>, format: OutputFormat) -> Result<()> {
+    let mut operations = read(&workspace.path)?;
+    if let Some(limit) = limit {
+        operations.drain(..operations.len().saturating_sub(limit));
+    }
+
+    if format == OutputFormat::Json {
+        return print_json(&operations);
+    }
+
+    for operation in operations {
+        println!(
+            "{} {} nut {}",
+            operation.id,
+            operation.timestamp.format("%Y-%m-%d %H:%M:%S"),
+            operation.args.join(" ")
+        );
+        for repo in &operation.repos {
+            if repo.before == repo.after {
+                println!("  {}: {}", repo.repo, repo.result);
+            } else {
+                println!(
+                    "  {}: {} ({} -> {})",
+                    repo.repo,
+                    repo.result,
+                    describe_head(&repo.before),
+                    describe_head(&repo.after)
+                );
+            }
+        }
+        println!();
+    }
+    Ok(())
+}
+
+/// Revert the ref changes of the most recent operation that was not undone yet.
+///
+/// Repositories whose HEAD changed since the operation are left alone.
+pub async fn undo(workspace: &Workspace) -> Result<()> {
+    let operations = read(&workspace.path)?;
+    let undone: HashSet<&str> = operations
+        .iter()
+        .filter_map(|o| o.undoes.as_deref())
+        .collect();
+    let operation = operations
+        .iter()
+        .rev()
+        .find(|o| o.undoes.is_none() && !undone.contains(o.id.as_str()))
+        .ok_or(NutError::NothingToUndo)?;
+
+    println!(
+        "Undoing {} (nut {})",
+        operation.id,
+        operation.args.join(" ")
+    );
+
+    let mut repos = Vec::new();
+    for entry in operation.repos.iter().filter(|r| r.before != r.after) {
+        let repo_dir = workspace.path.join(&entry.repo);
+        let current = git::head_state(&repo_dir).await;
+        let result = if current != entry.after {
+            "skipped, changed since".to_string()
+        } else {
+            match revert_head(&repo_dir, &entry.before).await {
+                Ok(true) => "reverted".to_string(),
+                Ok(false) => "skipped, no previous commit".to_string(),
+                Err(e) => format!("failed, {e}"),
+            }
+        };
+        println!("  {}: {}", entry.repo, result);
+        repos.push(RepoEntry {
+            repo: entry.repo.clone(),
+            result,
+            before: current,
+            after: git::head_state(&repo_dir).await,
+        });
+    }
+
+    append(
+        &workspace.path,
+        &Operation {
+            id: Ulid::new().to_string(),
+            timestamp: Utc::now(),
+            command: "undo".to_string(),
+            args: invocation_args(),
+            repos,
+            undoes: Some(operation.id.clone()),
+        },
+    )
+}
+
+async fn revert_head(repo_dir: &Path, before: &HeadState) -> Result<bool> {
+    let Some(commit) = &before.commit else {
+        return Ok(false);
+    };
+
+    match &before.branch {
+        Some(branch) => {
+            GitCommand::new(repo_dir)
+                .args(&["checkout", "--quiet", branch])
+                .run()
+                .await?
+        }
+        None => {
+            GitCommand::new(repo_dir)
+                .args(&["checkout", "--quiet", "--detach", commit])
+                .run()
+                .await?
+        }
+    }
+
+    // the branch itself might have moved, e.g. after a commit
+    if git::head_state(repo_dir).await.commit.as_ref() != Some(commit) {
+        GitCommand::new(repo_dir)
+            .args(&["reset", "--quiet", "--keep", commit])
+            .run()
+            .await?;
+    }
+
+    Ok(true)
+}
+
+fn describe_head(head: &HeadState) -> String {
+    let commit = head
+        .commit
+        .as_deref()
+        .map(|c| &c[..c.len().min(7)])
+        .unwrap_or("(none)");
+    match &head.branch {
+        Some(branch) => format!("{branch} {commit}"),
+        None => commit.to_string(),
+    }
+}
+
+async fn head_states(workspace_path: &Path) -> Result<BTreeMap<String, HeadState>> {
+    let repos = git::find_repositories(workspace_path)?;
+    let futures = repos.into_iter().map(|repo| async move {
+        let state = git::head_state(&workspace_path.join(&repo)).await;
+        (repo.to_string_lossy().to_string(), state)
+    });
+    Ok(futures_util::future::join_all(futures)
+        .await
+        .into_iter()
+        .collect())
+}
+
+fn append(workspace_path: &Path, operation: &Operation) -> Result<()> {
+    let path = log_path(workspace_path);
+    let mut line = serde_json::to_string(operation).map_err(|e| NutError::SerializeFailed {
+        source: Box::new(e),
+    })?;
+    line.push('\n');
+
+    std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&path)
+        .and_then(|mut f| f.write_all(line.as_bytes()))
+        .map_err(|e| NutError::WriteFileFailed { path, source: e })
+}
+
+fn invocation_args() -> Vec<String> {
+    std::env::args_os()
+        .skip(1)
+        .map(|a| a.to_string_lossy().to_string())
+        .collect()
+}
+
+fn log_path(workspace_path: &Path) -> PathBuf {
+    workspace_path.join(".nut").join(LOG_FILE)
+}
@@ -0,0 +1,94 @@
+use std::io::{IsTerminal, Write};
+use std::path::Path;
+use std::process::Stdio;
+
+use crate::error::{NutError, Result};
+use crate::git::GitCommand;
+use serde::Serialize;
+
+/// Output format for commands that support machine readable output
+#[derive(clap::ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum OutputFormat {
+    #[default]
+    Text,
+    Json,
+}
+
+pub fn print_json<T: Serialize + ?Sized>(value: &T) -> Result<()> {
+    let json = serde_json::to_string_pretty(value).map_err(|e| NutError::SerializeFailed {
+        source: Box::new(e),
+    })?;
+    println!("{json}");
+    Ok(())
+}
+
+/// Format a byte count using binary units, e.g. `1.5 MiB`
+pub fn human_size(bytes: u64) -> String {
+    const UNITS: [&str; 5] = ["B", "KiB", "MiB", "GiB", "TiB"];
+    let mut size = bytes as f64;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{bytes} B")
+    } else {
+        format!("{size:.1} {}", UNITS[unit])
+    }
+}
+
+/// Width of a table column that fits the header and all values
+pub fn column_width(header: &str, values: impl Iterator<Item = String>) -> usize {
+    values
+        .map(|v| v.chars().count())
+        .fold(header.len(), usize::max)
+}
+
+/// Show output through the pager git is configured to use, if stdout is a terminal
+pub async fn page(output: &[u8], working_dir: &Path) -> Result<()> {
+    let pager = GitCommand::new(working_dir)
+        .args(&["var", "GIT_PAGER"])
+        .output_string()
+        .await
+        .unwrap_or_default();
+
+    if !std::io::stdout().is_terminal() || pager.is_empty() || pager == "cat" {
+        // ignore errors, e.g. when the output is piped into head
+        std::io::stdout().write_all(output).ok();
+        return Ok(());
+    }
+
+    let pager_failed = |e| NutError::PagerFailed {
+        pager: pager.clone(),
+        source: e,
+    };
+    // same defaults as git uses for less
+    let mut child = tokio::process::Command::new("sh")
+        .args(["-c", &pager])
+        .env("LESS", std::env::var("LESS").unwrap_or("FRX".to_string()))
+        .stdin(Stdio::piped())
+        .spawn()
+        .map_err(pager_failed)?;
+
+    if let Some(mut stdin) = child.stdin.take() {
+        use tokio::io::AsyncWriteExt;
+        // the pager might exit before reading everything, e.g. when quitting less early
+        stdin.write_all(output).await.ok();
+    }
+    child.wait().await.map_err(pager_failed)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_human_size() {
+        assert_eq!(human_size(0), "0 B");
+        assert_eq!(human_size(1023), "1023 B");
+        assert_eq!(human_size(1536), "1.5 KiB");
+        assert_eq!(human_size(3 * 1024 * 1024 * 1024), "3.0 GiB");
+    }
+}
@@ -0,0 +1,36 @@
+use std::io::Write;
+
+use crate::error::Result;
+
+/// Answer to a `[y,n,a,q]` confirmation prompt
+pub enum Answer {
+    Yes,
+    No,
+    /// Yes, and don't ask again
+    All,
+    /// No, and stop altogether
+    Quit,
+}
+
+/// Ask a question until it is answered with y, n, a or q. `read_line` returns `None`
+/// at the end of input, which counts as quitting
+pub async fn ask(
+    question: &str,
+    mut read_line: impl AsyncFnMut() -> Result<Option<String>>,
+) -> Result<Answer> {
+    loop {
+        print!("{question} [y,n,a,q] ");
+        std::io::stdout().flush().ok();
+        let Some(line) = read_line().await? else {
+            println!();
+            return Ok(Answer::Quit);
+        };
+        match line.trim() {
+            "y" => return Ok(Answer::Yes),
+            "n" => return Ok(Answer::No),
+            "a" => return Ok(Answer::All),
+            "q" => return Ok(Answer::Quit),
+            _ => {}
+        }
+    }
+}
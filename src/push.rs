@@ -0,0 +1,158 @@
+use std::path::{Path, PathBuf};
+
+use futures_util::StreamExt;
+
+use crate::error::{NutError, Result};
+use crate::git::{self, GitCommand};
+use crate::output::column_width;
+use crate::workspace::Workspace;
+
+const REMOTE: &str = "origin";
+
+/// Pushes running at the same time, more trip connection limits of SSH servers and
+/// rate limits of hosts
+const CONCURRENT_PUSHES: usize = 8;
+
+pub struct PushOptions {
+    /// Also push branches that are the default branch of the remote
+    pub allow_default_branch: bool,
+    pub force_with_lease: bool,
+}
+
+struct PushResult {
+    repo: PathBuf,
+    branch: String,
+    result: String,
+    /// The push failed or was refused
+    failed: bool,
+}
+
+/// Push the current branch of every repository that is ahead of its upstream
+pub async fn push(workspace: &Workspace, options: &PushOptions) -> Result<()> {
+    let repos = git::find_repositories(&workspace.path)?;
+    let mut results: Vec<_> = futures_util::stream::iter(repos)
+        .map(|repo| push_repo(&workspace.path, repo, options))
+        .buffer_unordered(CONCURRENT_PUSHES)
+        .collect()
+        .await;
+    results.sort_by(|a, b| a.repo.cmp(&b.repo));
+
+    let repo_width = column_width(
+        "REPOSITORY",
+        results.iter().map(|r| r.repo.display().to_string()),
+    );
+    let branch_width = column_width("BRANCH", results.iter().map(|r| r.branch.clone()));
+    println!(
+        "{:repo_width$}  {:branch_width$}  RESULT",
+        "REPOSITORY", "BRANCH"
+    );
+    for result in &results {
+        println!(
+            "{:repo_width$}  {:branch_width$}  {}",
+            result.repo.display().to_string(),
+            result.branch,
+            result.result
+        );
+    }
+
+    let failed = results.iter().filter(|r| r.failed).count();
+    if failed > 0 {
+        return Err(NutError::PushFailed { failed });
+    }
+    Ok(())
+}
+
+async fn push_repo(workspace_path: &Path, repo: PathBuf, options: &PushOptions) -> PushResult {
+    let repo_dir = workspace_path.join(&repo);
+    let Some(branch) = git::head_state(&repo_dir).await.branch else {
+        return PushResult {
+            repo,
+            branch: "-".to_string(),
+            result: "skipped, detached HEAD".to_string(),
+            failed: false,
+        };
+    };
+    let (result, failed) = match push_branch(&repo_dir, &branch, options).await {
+        Ok(result) => (result, false),
+        Err(result) => (result, true),
+    };
+    PushResult {
+        repo,
+        branch,
+        result,
+        failed,
+    }
+}
+
+/// Push a branch, returning what happened or why the push failed or was refused
+async fn push_branch(
+    repo_dir: &Path,
+    branch: &str,
+    options: &PushOptions,
+) -> std::result::Result<String, String> {
+    // branches created from e.g. origin/main track it, but are pushed to a branch of their own name
+    let upstream = GitCommand::new(repo_dir)
+        .args(&[
+            "rev-parse",
+            "--abbrev-ref",
+            "--symbolic-full-name",
+            "@{upstream}",
+        ])
+        .checked_output_string()
+        .await
+        .ok();
+    let has_upstream = upstream == Some(format!("{REMOTE}/{branch}"));
+    if has_upstream {
+        let ahead = GitCommand::new(repo_dir)
+            .args(&["rev-list", "--count", "@{upstream}..HEAD"])
+            .checked_output_string()
+            .await
+            .ok()
+            .and_then(|count| count.parse::<usize>().ok());
+        if ahead == Some(0) {
+            return Ok("up to date".to_string());
+        }
+    }
+
+    if !options.allow_default_branch {
+        match git::query_remote_default_branch(repo_dir).await {
+            Some(default_branch) if default_branch == branch => {
+                return Err("refused, default branch (use --allow-default-branch)".to_string());
+            }
+            Some(_) => {}
+            // without knowing the default branch, this could be it
+            None => {
+                return Err(
+                    "refused, unknown default branch of origin (use --allow-default-branch)"
+                        .to_string(),
+                );
+            }
+        }
+    }
+
+    let mut command = GitCommand::new(repo_dir).args(&["push", "--quiet"]);
+    if options.force_with_lease {
+        command = command.arg("--force-with-lease");
+    }
+    if !has_upstream {
+        command = command.arg("--set-upstream");
+    }
+    let output = command.args(&[REMOTE, branch]).output().await;
+
+    match output {
+        Ok(output) if output.status.success() && has_upstream => Ok("pushed".to_string()),
+        Ok(output) if output.status.success() => {
+            Ok(format!("pushed, upstream set to {REMOTE}/{branch}"))
+        }
+        Ok(output) => {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            let reason = stderr
+                .lines()
+                .map(|l| l.trim())
+                .find(|l| l.starts_with("error:") || l.starts_with("fatal:") || l.starts_with("!"))
+                .unwrap_or("push failed");
+            Err(format!("failed, {reason}"))
+        }
+        Err(e) => Err(format!("failed, {e}")),
+    }
+}
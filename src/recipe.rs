@@ -0,0 +1,164 @@
+use std::path::{Path, PathBuf};
+
+use crate::config::NutConfig;
+use crate::error::{NutError, Result};
+
+/// Header lines look like `# nut: description: Bump the node version`
+const HEADER_PREFIX: &str = "# nut:";
+
+/// A named script in the recipes directory
+pub struct Recipe {
+    pub name: String,
+    pub path: PathBuf,
+    pub description: Option<String>,
+    /// Programs that must be on the PATH
+    pub requires: Vec<String>,
+    /// Shell command deciding whether the recipe applies to a repository
+    pub applies_if: Option<String>,
+}
+
+impl Recipe {
+    /// Find a recipe by file name, with or without extension
+    pub fn find(name: &str) -> Result<Self> {
+        Self::all()?
+            .into_iter()
+            .find(|r| r.name == name || r.path.file_name().is_some_and(|f| f == name))
+            .ok_or_else(|| NutError::RecipeNotFound {
+                name: name.to_string(),
+            })
+    }
+
+    pub fn all() -> Result<Vec<Self>> {
+        let dir = recipes_dir()?;
+        let entries = std::fs::read_dir(&dir).map_err(|e| NutError::ReadDirectoryFailed {
+            path: dir.clone(),
+            source: e,
+        })?;
+
+        let mut recipes = Vec::new();
+        for entry in entries.filter_map(|e| e.ok()) {
+            let path = entry.path();
+            let hidden = entry.file_name().to_string_lossy().starts_with('.');
+            if hidden || !path.is_file() {
+                continue;
+            }
+            recipes.push(Self::load(&path)?);
+        }
+        recipes.sort_by(|a, b| a.name.cmp(&b.name));
+        Ok(recipes)
+    }
+
+    fn load(path: &Path) -> Result<Self> {
+        let content = std::fs::read(path).map_err(|e| NutError::ReadFileFailed {
+            path: path.to_path_buf(),
+            source: e,
+        })?;
+        let mut recipe = Self {
+            name: path
+                .file_stem()
+                .unwrap_or_default()
+                .to_string_lossy()
+                .to_string(),
+            path: path.to_path_buf(),
+            description: None,
+            requires: Vec::new(),
+            applies_if: None,
+        };
+
+        // the header is the comment block at the start of the file, after the shebang
+        let content = String::from_utf8_lossy(&content);
+        for line in content
+            .lines()
+            .skip_while(|l| l.starts_with("#!"))
+            .take_while(|l| l.starts_with('#') || l.trim().is_empty())
+        {
+            let Some((key, value)) = line
+                .strip_prefix(HEADER_PREFIX)
+                .and_then(|h| h.split_once(':'))
+            else {
+                continue;
+            };
+            let value = value.trim().to_string();
+            match key.trim() {
+                "description" => recipe.description = Some(value),
+                "requires" => recipe.requires.extend(
+                    value
+                        .split([',', ' '])
+                        .filter(|t| !t.is_empty())
+                        .map(|t| t.to_string()),
+                ),
+                "applies-if" => recipe.applies_if = Some(value),
+                _ => {}
+            }
+        }
+        Ok(recipe)
+    }
+
+    /// Required programs that are not on the PATH
+    pub fn missing_tools(&self) -> Vec<String> {
+        missing_tools(&self.requires)
+    }
+}
+
+/// Programs that are not on the PATH
+pub fn missing_tools(tools: &[String]) -> Vec<String> {
+    let path = std::env::var_os("PATH").unwrap_or_default();
+    tools
+        .iter()
+        .filter(|tool| !std::env::split_paths(&path).any(|dir| dir.join(tool).is_file()))
+        .cloned()
+        .collect()
+}
+
+pub fn recipes_dir() -> Result<PathBuf> {
+    NutConfig::load()?
+        .recipes_dir
+        .ok_or(NutError::RecipesDirectoryNotConfigured)
+}
+
+pub fn list() -> Result<()> {
+    for recipe in Recipe::all()? {
+        println!("{}", recipe.name);
+        if let Some(description) = &recipe.description {
+            println!("  {description}");
+        }
+        if !recipe.requires.is_empty() {
+            println!("  Requires: {}", recipe.requires.join(", "));
+        }
+        if let Some(applies_if) = &recipe.applies_if {
+            println!("  Applies if: {applies_if}");
+        }
+        println!();
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parses_header() {
+        let dir = std::env::temp_dir().join(format!("nut-recipe-{}", ulid::Ulid::new()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("bump-node.sh");
+        std::fs::write(
+            &path,
+            "#!/bin/sh\n\
+             # nut: description: Bump node\n\
+             # nut: requires: jq, node\n\
+             # Not part of the header: # nut: requires: ignored\n\
+             # nut: applies-if: test -f package.json\n\
+             echo\n\
+             # nut: description: not in the header\n",
+        )
+        .unwrap();
+
+        let recipe = Recipe::load(&path).unwrap();
+        std::fs::remove_dir_all(&dir).ok();
+        assert_eq!(recipe.name, "bump-node");
+        assert_eq!(recipe.description.as_deref(), Some("Bump node"));
+        assert_eq!(recipe.requires, ["jq", "node"]);
+        assert_eq!(recipe.applies_if.as_deref(), Some("test -f package.json"));
+    }
+}
@@ -0,0 +1,194 @@
+use std::path::{Path, PathBuf};
+
+use globset::{Glob, GlobSet, GlobSetBuilder};
+use regex::{NoExpand, Regex};
+use similar::TextDiff;
+
+use crate::error::{NutError, Result};
+use crate::git::{self, GitCommand};
+use crate::prompt::{self, Answer};
+use crate::workspace::Workspace;
+
+pub struct ReplaceOptions {
+    pub pattern: String,
+    /// Replacement, may refer to capture groups like `$1` unless `fixed_strings` is set
+    pub replacement: String,
+    /// Treat pattern and replacement as literal strings
+    pub fixed_strings: bool,
+    /// Only change files matching one of these globs
+    pub globs: Vec<String>,
+    /// Show the changes without writing them
+    pub dry_run: bool,
+    /// Ask before changing each file
+    pub interactive: bool,
+}
+
+/// Replace matches in all files tracked or not ignored in every repository
+pub async fn replace(workspace: &Workspace, options: &ReplaceOptions) -> Result<()> {
+    let pattern = if options.fixed_strings {
+        regex::escape(&options.pattern)
+    } else {
+        options.pattern.clone()
+    };
+    let regex = Regex::new(&pattern).map_err(|e| NutError::InvalidRegex {
+        pattern: options.pattern.clone(),
+        source: e,
+    })?;
+    let globs = build_globs(&options.globs)?;
+
+    let (mut apply_all, mut quit) = (false, false);
+    let (mut total_replacements, mut total_files) = (0, 0);
+    for repo in git::find_repositories(&workspace.path)? {
+        let repo_dir = workspace.path.join(&repo);
+        let (mut replacements, mut files) = (0, 0);
+
+        for path in list_files(&repo_dir).await? {
+            if !globs.is_match(&path) {
+                continue;
+            }
+            let file = repo_dir.join(&path);
+            // renaming over a symlink would replace it with a regular file
+            if !std::fs::symlink_metadata(&file).is_ok_and(|m| m.is_file()) {
+                continue;
+            }
+            // skip binary and unreadable files
+            let Ok(content) = std::fs::read_to_string(&file) else {
+                continue;
+            };
+            let count = regex.find_iter(&content).count();
+            if count == 0 {
+                continue;
+            }
+            let replaced = if options.fixed_strings {
+                regex.replace_all(&content, NoExpand(&options.replacement))
+            } else {
+                regex.replace_all(&content, options.replacement.as_str())
+            };
+            if replaced == content {
+                continue;
+            }
+
+            let display_path = repo.join(&path);
+            if options.dry_run || options.interactive {
+                print_diff(&display_path, &content, &replaced);
+            }
+            if options.interactive && !apply_all {
+                let question = format!("Apply changes to {}?", display_path.display());
+                match prompt::ask(&question, async || read_line()).await? {
+                    Answer::Yes => {}
+                    Answer::No => continue,
+                    Answer::All => apply_all = true,
+                    Answer::Quit => {
+                        quit = true;
+                        break;
+                    }
+                }
+            }
+            if !options.dry_run {
+                write_atomic(&file, replaced.as_bytes())?;
+            }
+            replacements += count;
+            files += 1;
+        }
+
+        if files > 0 {
+            println!(
+                "{}: {replacements} replacement(s) in {files} file(s)",
+                repo.display()
+            );
+        }
+        total_replacements += replacements;
+        total_files += files;
+        if quit {
+            break;
+        }
+    }
+
+    let verb = if options.dry_run {
+        "Would make"
+    } else {
+        "Made"
+    };
+    println!("{verb} {total_replacements} replacement(s) in {total_files} file(s)");
+    Ok(())
+}
+
+fn build_globs(globs: &[String]) -> Result<GlobSet> {
+    let mut builder = GlobSetBuilder::new();
+    if globs.is_empty() {
+        builder.add(Glob::new("*").expect("valid glob"));
+    }
+    for glob in globs {
+        builder.add(Glob::new(glob).map_err(|e| NutError::InvalidGlob {
+            glob: glob.clone(),
+            source: e,
+        })?);
+    }
+    builder.build().map_err(|e| NutError::InvalidGlob {
+        glob: globs.join(", "),
+        source: e,
+    })
+}
+
+/// Files that are tracked or untracked but not ignored
+async fn list_files(repo_dir: &Path) -> Result<Vec<PathBuf>> {
+    let output = GitCommand::new(repo_dir)
+        .args(&[
+            "ls-files",
+            "-z",
+            "--cached",
+            "--others",
+            "--exclude-standard",
+            "--deduplicate",
+        ])
+        .checked_output()
+        .await?;
+    Ok(String::from_utf8_lossy(&output)
+        .split('\0')
+        .filter(|p| !p.is_empty())
+        .map(PathBuf::from)
+        .collect())
+}
+
+fn print_diff(path: &Path, old: &str, new: &str) {
+    let old_header = format!("a/{}", path.display());
+    let new_header = format!("b/{}", path.display());
+    print!(
+        "{}",
+        TextDiff::from_lines(old, new)
+            .unified_diff()
+            .context_radius(3)
+            .header(&old_header, &new_header)
+    );
+}
+
+/// A line from stdin, or `None` at the end of input
+fn read_line() -> Result<Option<String>> {
+    let mut line = String::new();
+    let read = std::io::stdin()
+        .read_line(&mut line)
+        .map_err(|e| NutError::ReadStdinFailed { source: e })?;
+    Ok((read > 0).then_some(line))
+}
+
+/// Write to a temporary file next to the target and rename it over the target,
+/// keeping the original permissions
+pub fn write_atomic(path: &Path, content: &[u8]) -> Result<()> {
+    let write_failed = |e| NutError::WriteFileFailed {
+        path: path.to_path_buf(),
+        source: e,
+    };
+    let permissions = std::fs::metadata(path).map_err(write_failed)?.permissions();
+
+    let mut temp_name = path.file_name().unwrap_or_default().to_os_string();
+    temp_name.push(".nut-replace");
+    let temp = path.with_file_name(temp_name);
+
+    let result = std::fs::write(&temp, content)
+        .and_then(|_| std::fs::set_permissions(&temp, permissions))
+        .and_then(|_| std::fs::rename(&temp, path));
+    if result.is_err() {
+        std::fs::remove_file(&temp).ok();
+    }
+    result.map_err(write_failed)
+}
@@ -0,0 +1,259 @@
+use std::path::{Path, PathBuf};
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use ulid::Ulid;
+
+use crate::apply::Condition;
+use crate::error::{NutError, Result};
+use crate::workspace::Workspace;
+
+const RUN_FILE: &str = "run.json";
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Outcome {
+    Success,
+    Failed,
+    Skipped,
+    /// The run was aborted before reaching the repository
+    NotRun,
+}
+
+/// Result of an apply run in a single repository
+#[derive(Serialize, Deserialize)]
+pub struct RepoRun {
+    pub repo: String,
+    pub outcome: Outcome,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub exit_code: Option<i32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub message: Option<String>,
+    pub duration_ms: u64,
+}
+
+impl RepoRun {
+    /// Result for a repository where the command was not started
+    pub fn not_started(repo: &Path, outcome: Outcome, message: impl Into<String>) -> Self {
+        Self {
+            repo: repo.to_string_lossy().to_string(),
+            outcome,
+            exit_code: None,
+            message: Some(message.into()),
+            duration_ms: 0,
+        }
+    }
+}
+
+/// An apply run, stored in `.nut/runs/<id>/run.json` next to the captured output
+#[derive(Serialize, Deserialize)]
+pub struct Run {
+    pub id: String,
+    pub started: DateTime<Utc>,
+    pub command: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rerun_of: Option<String>,
+    /// Directory with the output of every repository, if not stored with the run
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub log_dir: Option<PathBuf>,
+    /// Recipe the command comes from, and the programs it requires
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub recipe: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub requires: Vec<String>,
+    /// Conditions deciding which repositories are skipped, also applied to re-runs
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub conditions: Vec<Condition>,
+    /// Stopped by a signal or the user before all repositories were run
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub aborted: bool,
+    pub repos: Vec<RepoRun>,
+}
+
+impl Run {
+    pub fn new(command: Vec<String>, rerun_of: Option<String>) -> Self {
+        Self {
+            id: Ulid::new().to_string(),
+            started: Utc::now(),
+            command,
+            rerun_of,
+            log_dir: None,
+            recipe: None,
+            requires: Vec::new(),
+            conditions: Vec::new(),
+            aborted: false,
+            repos: Vec::new(),
+        }
+    }
+
+    /// Load a run by ID, or the most recent run if no ID is given
+    pub fn load(workspace_path: &Path, id: Option<&str>) -> Result<Self> {
+        let id = match id {
+            Some(id) => id.to_string(),
+            None => {
+                let mut ids = run_ids(workspace_path)?;
+                ids.sort();
+                ids.pop().ok_or_else(|| NutError::RunNotFound {
+                    id: "(latest)".to_string(),
+                })?
+            }
+        };
+
+        let path = runs_dir(workspace_path).join(&id).join(RUN_FILE);
+        let json = std::fs::read(&path).map_err(|_| NutError::RunNotFound { id: id.clone() })?;
+        serde_json::from_slice(&json).map_err(|e| NutError::InvalidRun {
+            path,
+            reason: e.to_string(),
+        })
+    }
+
+    pub fn save(&self, workspace_path: &Path) -> Result<()> {
+        let dir = self.dir(workspace_path);
+        std::fs::create_dir_all(&dir).map_err(|e| NutError::CreateDirectoryFailed {
+            path: dir.clone(),
+            source: e,
+        })?;
+        let json = serde_json::to_vec_pretty(self).map_err(|e| NutError::SerializeFailed {
+            source: Box::new(e),
+        })?;
+        let path = dir.join(RUN_FILE);
+        std::fs::write(&path, json).map_err(|e| NutError::WriteFileFailed { path, source: e })
+    }
+
+    pub fn dir(&self, workspace_path: &Path) -> PathBuf {
+        runs_dir(workspace_path).join(&self.id)
+    }
+
+    /// File containing the captured stdout and stderr of a repository
+    pub fn output_path(&self, workspace_path: &Path, repo: &Path) -> PathBuf {
+        let log_dir = match &self.log_dir {
+            Some(log_dir) => log_dir.clone(),
+            None => self.dir(workspace_path).join("output"),
+        };
+        let mut path = log_dir.join(repo);
+        path.as_mut_os_string().push(".log");
+        path
+    }
+
+    /// Repositories where the command failed or was not run because the run was aborted
+    pub fn failed_repos(&self) -> Vec<PathBuf> {
+        self.repos
+            .iter()
+            .filter(|r| matches!(r.outcome, Outcome::Failed | Outcome::NotRun))
+            .map(|r| PathBuf::from(&r.repo))
+            .collect()
+    }
+
+    pub fn count(&self, outcome: Outcome) -> usize {
+        self.repos.iter().filter(|r| r.outcome == outcome).count()
+    }
+
+    /// Counts of the outcomes, like "3 succeeded, 1 failed, 2 skipped"
+    pub fn summary(&self) -> String {
+        let mut summary = format!(
+            "{} succeeded, {} failed",
+            self.count(Outcome::Success),
+            self.count(Outcome::Failed)
+        );
+        for (outcome, label) in [(Outcome::Skipped, "skipped"), (Outcome::NotRun, "not run")] {
+            let count = self.count(outcome);
+            if count > 0 {
+                summary.push_str(&format!(", {count} {label}"));
+            }
+        }
+        summary
+    }
+}
+
+pub fn list(workspace: &Workspace) -> Result<()> {
+    let mut ids = run_ids(&workspace.path)?;
+    ids.sort();
+
+    for id in ids {
+        let run = Run::load(&workspace.path, Some(&id))?;
+        println!(
+            "{} {} {}",
+            run.id,
+            run.started.format("%Y-%m-%d %H:%M:%S"),
+            run.command.join(" ")
+        );
+        println!("  {}", run.summary());
+        println!();
+    }
+    Ok(())
+}
+
+/// Print the results of a run, or the captured output of a single repository
+pub fn show(workspace: &Workspace, id: &str, repo: Option<&str>) -> Result<()> {
+    let run = Run::load(&workspace.path, Some(id))?;
+
+    if let Some(repo) = repo {
+        let result = run.repos.iter().find(|r| r.repo == repo);
+        // the command did not run there, so there is no output
+        if let Some(result) = result
+            && matches!(result.outcome, Outcome::Skipped | Outcome::NotRun)
+        {
+            println!("{}", describe(result));
+            return Ok(());
+        }
+        let path = run.output_path(&workspace.path, Path::new(repo));
+        if !path.exists() && result.is_some() {
+            println!("No output was captured for {repo}");
+            return Ok(());
+        }
+        let output =
+            std::fs::read(&path).map_err(|e| NutError::ReadFileFailed { path, source: e })?;
+        print!("{}", String::from_utf8_lossy(&output));
+        return Ok(());
+    }
+
+    println!("Run {}", run.id);
+    println!("  Started: {}", run.started.format("%Y-%m-%d %H:%M:%S"));
+    println!("  Command: {}", run.command.join(" "));
+    if let Some(rerun_of) = &run.rerun_of {
+        println!("  Re-run of: {rerun_of}");
+    }
+    println!();
+    for repo in &run.repos {
+        println!("  {}", describe(repo));
+    }
+    Ok(())
+}
+
+/// Result of a repository like "org/repo: failed after 1.2s (Command exited with status code 1)"
+fn describe(repo: &RepoRun) -> String {
+    let outcome = match repo.outcome {
+        Outcome::Success => "ok",
+        Outcome::Failed => "failed",
+        Outcome::Skipped => "skipped",
+        Outcome::NotRun => "not run",
+    };
+    let mut line = format!("{}: {}", repo.repo, outcome);
+    if matches!(repo.outcome, Outcome::Success | Outcome::Failed) {
+        line.push_str(&format!(" after {:.1}s", repo.duration_ms as f64 / 1000.0));
+    }
+    if let Some(message) = &repo.message {
+        line.push_str(&format!(" ({message})"));
+    }
+    line
+}
+
+fn runs_dir(workspace_path: &Path) -> PathBuf {
+    workspace_path.join(".nut").join("runs")
+}
+
+fn run_ids(workspace_path: &Path) -> Result<Vec<String>> {
+    let dir = runs_dir(workspace_path);
+    if !dir.exists() {
+        return Ok(Vec::new());
+    }
+    let entries = std::fs::read_dir(&dir).map_err(|e| NutError::ReadDirectoryFailed {
+        path: dir.clone(),
+        source: e,
+    })?;
+    Ok(entries
+        .filter_map(|e| e.ok())
+        .filter_map(|e| e.file_name().into_string().ok())
+        .filter(|name| Ulid::from_string(name).is_ok())
+        .collect())
+}
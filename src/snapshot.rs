@@ -0,0 +1,323 @@
+use std::collections::BTreeMap;
+use std::ffi::OsStr;
+use std::path::{Path, PathBuf};
+
+use chrono::Utc;
+
+use crate::error::{NutError, Result};
+use crate::git::{self, GitCommand};
+use crate::workspace::{self, Workspace};
+
+const SNAPSHOT_REFS: &str = "refs/nut/snapshots";
+
+/// Snapshot commits are internal, so they use a fixed identity instead of
+/// requiring the user to have one configured
+const IDENTITY: [(&str, &str); 4] = [
+    ("GIT_AUTHOR_NAME", "nut"),
+    ("GIT_AUTHOR_EMAIL", "nut@localhost"),
+    ("GIT_COMMITTER_NAME", "nut"),
+    ("GIT_COMMITTER_EMAIL", "nut@localhost"),
+];
+
+/// Record HEAD, branch, index and working tree of every repository.
+///
+/// Each snapshot is a commit stored under `refs/nut/snapshots/<name>`. Its tree
+/// is the working tree (including untracked files), its first parent is HEAD and
+/// its second parent is a commit containing the index.
+pub async fn create(workspace: &Workspace, name: &str) -> Result<()> {
+    validate_name(name)?;
+
+    let repos = git::find_repositories(&workspace.path)?;
+    for repo in &repos {
+        if snapshot_commit(&workspace.path.join(repo), name)
+            .await
+            .is_some()
+        {
+            return Err(NutError::SnapshotExists {
+                name: name.to_string(),
+            });
+        }
+    }
+
+    for (repo, saved) in save(workspace, &repos, name).await? {
+        match saved {
+            true => println!("{}: saved", repo.display()),
+            false => println!("{}: skipped, repository has no commits", repo.display()),
+        }
+    }
+    println!("Created snapshot {name}");
+
+    Ok(())
+}
+
+/// Roll back every repository to the state recorded in a snapshot.
+///
+/// Restoring discards uncommitted work, so the current state is saved as a
+/// `pre-restore-<time>` snapshot first.
+pub async fn restore(workspace: &Workspace, name: &str) -> Result<()> {
+    let mut repos = Vec::new();
+    let mut commits = Vec::new();
+    for repo in git::find_repositories(&workspace.path)? {
+        match snapshot_commit(&workspace.path.join(&repo), name).await {
+            Some(commit) => {
+                repos.push(repo);
+                commits.push(commit);
+            }
+            None => println!("{}: skipped, not part of the snapshot", repo.display()),
+        }
+    }
+    if repos.is_empty() {
+        return Err(NutError::SnapshotNotFound {
+            name: name.to_string(),
+        });
+    }
+
+    let backup = format!("pre-restore-{}", Utc::now().format("%Y%m%d-%H%M%S"));
+    save(workspace, &repos, &backup).await?;
+    println!("Saved the current state as snapshot {backup}");
+
+    for (repo, commit) in repos.iter().zip(&commits) {
+        restore_in_repo(&workspace.path.join(repo), commit).await?;
+        println!("{}: restored", repo.display());
+    }
+    Ok(())
+}
+
+pub async fn delete(workspace: &Workspace, name: &str) -> Result<()> {
+    let mut found = false;
+    for repo in git::find_repositories(&workspace.path)? {
+        let repo_dir = workspace.path.join(&repo);
+        if snapshot_commit(&repo_dir, name).await.is_some() {
+            delete_ref(&repo_dir, name).await?;
+            found = true;
+        }
+    }
+
+    if !found {
+        return Err(NutError::SnapshotNotFound {
+            name: name.to_string(),
+        });
+    }
+    Ok(())
+}
+
+pub async fn list(workspace: &Workspace) -> Result<()> {
+    // snapshot name -> (creation date, number of repositories)
+    let mut snapshots: BTreeMap<String, (String, usize)> = BTreeMap::new();
+    for repo in git::find_repositories(&workspace.path)? {
+        let refs = GitCommand::new(&workspace.path.join(&repo))
+            .args(&[
+                "for-each-ref",
+                "--format=%(refname:lstrip=3) %(creatordate:format:%Y-%m-%d %H:%M:%S)",
+                SNAPSHOT_REFS,
+            ])
+            .output_string()
+            .await?;
+        for line in refs.lines() {
+            if let Some((name, date)) = line.split_once(' ') {
+                let entry = snapshots
+                    .entry(name.to_string())
+                    .or_insert_with(|| (date.to_string(), 0));
+                entry.1 += 1;
+            }
+        }
+    }
+
+    let mut snapshots: Vec<_> = snapshots.into_iter().collect();
+    snapshots.sort_by(|a, b| b.1.0.cmp(&a.1.0));
+    for (name, (date, repos)) in snapshots {
+        println!("{name}");
+        println!("  Created: {date}");
+        println!("  {repos} repositories");
+        println!();
+    }
+
+    Ok(())
+}
+
+fn validate_name(name: &str) -> Result<()> {
+    // git refuses these in ref names
+    if workspace::is_valid_name(name) && !name.ends_with(".lock") && !name.contains("..") {
+        Ok(())
+    } else {
+        Err(NutError::InvalidSnapshotName {
+            name: name.to_string(),
+        })
+    }
+}
+
+/// Snapshot the given repositories. If one of them fails, the refs already
+/// written are removed so no partial snapshot is left behind. Returns whether
+/// each repository had anything to snapshot
+async fn save<'a>(
+    workspace: &Workspace,
+    repos: &'a [PathBuf],
+    name: &str,
+) -> Result<Vec<(&'a PathBuf, bool)>> {
+    let mut saved = Vec::new();
+    for repo in repos {
+        match create_in_repo(&workspace.path.join(repo), name).await {
+            Ok(created) => saved.push((repo, created)),
+            Err(e) => {
+                for (repo, created) in saved {
+                    if created {
+                        delete_ref(&workspace.path.join(repo), name).await.ok();
+                    }
+                }
+                return Err(e);
+            }
+        }
+    }
+    Ok(saved)
+}
+
+async fn delete_ref(repo_dir: &Path, name: &str) -> Result<()> {
+    GitCommand::new(repo_dir)
+        .args(&["update-ref", "-d", &format!("{SNAPSHOT_REFS}/{name}")])
+        .run()
+        .await
+}
+
+async fn snapshot_commit(repo_dir: &Path, name: &str) -> Option<String> {
+    GitCommand::new(repo_dir)
+        .args(&[
+            "rev-parse",
+            "--verify",
+            "--quiet",
+            &format!("{SNAPSHOT_REFS}/{name}^{{commit}}"),
+        ])
+        .checked_output_string()
+        .await
+        .ok()
+}
+
+/// Returns false if the repository has nothing to snapshot
+async fn create_in_repo(repo_dir: &Path, name: &str) -> Result<bool> {
+    let Ok(head) = GitCommand::new(repo_dir)
+        .args(&["rev-parse", "--verify", "--quiet", "HEAD"])
+        .checked_output_string()
+        .await
+    else {
+        return Ok(false);
+    };
+    let branch = GitCommand::new(repo_dir)
+        .args(&["branch", "--show-current"])
+        .output_string()
+        .await?;
+
+    let index_tree = GitCommand::new(repo_dir)
+        .arg("write-tree")
+        .checked_output_string()
+        .await?;
+    let worktree_tree = write_worktree_tree(repo_dir).await?;
+
+    let index_commit = commit_tree(repo_dir, &index_tree, &[&head], "index").await?;
+    let message = format!("nut snapshot {name}\n\nbranch: {branch}");
+    let commit = commit_tree(repo_dir, &worktree_tree, &[&head, &index_commit], &message).await?;
+
+    GitCommand::new(repo_dir)
+        .args(&["update-ref", &format!("{SNAPSHOT_REFS}/{name}"), &commit])
+        .run()
+        .await?;
+
+    Ok(true)
+}
+
+/// Write a tree of the working tree, including untracked but not ignored files,
+/// using a temporary copy of the index
+async fn write_worktree_tree(repo_dir: &Path) -> Result<String> {
+    let index = GitCommand::new(repo_dir)
+        .args(&["rev-parse", "--path-format=absolute", "--git-path", "index"])
+        .checked_output_string()
+        .await?;
+    let temp_index = format!("{index}.nut-snapshot");
+    if tokio::fs::try_exists(&index).await.unwrap_or(false) {
+        tokio::fs::copy(&index, &temp_index)
+            .await
+            .map_err(|e| NutError::WriteFileFailed {
+                path: temp_index.clone().into(),
+                source: e,
+            })?;
+    }
+
+    let temp_index_env = OsStr::new(&temp_index);
+    let result = async {
+        GitCommand::new(repo_dir)
+            .args(&["add", "--all"])
+            .env("GIT_INDEX_FILE", temp_index_env)
+            .checked_output()
+            .await?;
+        GitCommand::new(repo_dir)
+            .arg("write-tree")
+            .env("GIT_INDEX_FILE", temp_index_env)
+            .checked_output_string()
+            .await
+    }
+    .await;
+
+    tokio::fs::remove_file(&temp_index).await.ok();
+    result
+}
+
+async fn commit_tree(
+    repo_dir: &Path,
+    tree: &str,
+    parents: &[&str],
+    message: &str,
+) -> Result<String> {
+    let mut command = GitCommand::new(repo_dir).args(&["commit-tree", tree, "-m", message]);
+    for parent in parents {
+        command = command.args(&["-p", parent]);
+    }
+    for (key, value) in IDENTITY {
+        command = command.env(key, OsStr::new(value));
+    }
+    command.checked_output_string().await
+}
+
+async fn restore_in_repo(repo_dir: &Path, commit: &str) -> Result<()> {
+    let message = GitCommand::new(repo_dir)
+        .args(&["log", "-1", "--format=%B", commit])
+        .checked_output_string()
+        .await?;
+    let branch = message
+        .lines()
+        .find_map(|l| l.strip_prefix("branch: "))
+        .map(|b| b.trim().to_string())
+        .filter(|b| !b.is_empty());
+
+    let head = format!("{commit}^1");
+    let index_tree = format!("{commit}^2^{{tree}}");
+    let worktree_tree = format!("{commit}^{{tree}}");
+
+    match &branch {
+        Some(branch) => {
+            GitCommand::new(repo_dir)
+                .args(&["checkout", "--quiet", "--force", "-B", branch, &head])
+                .run()
+                .await?
+        }
+        None => {
+            GitCommand::new(repo_dir)
+                .args(&["checkout", "--quiet", "--force", "--detach", &head])
+                .run()
+                .await?
+        }
+    }
+
+    // remove untracked files, the snapshot tree brings back the ones that existed
+    GitCommand::new(repo_dir)
+        .args(&["clean", "--force", "-d", "--quiet"])
+        .run()
+        .await?;
+    GitCommand::new(repo_dir)
+        .args(&["read-tree", "--reset", "-u", &worktree_tree])
+        .run()
+        .await?;
+    GitCommand::new(repo_dir)
+        .args(&["read-tree", &index_tree])
+        .run()
+        .await?;
+
+    Ok(())
+}
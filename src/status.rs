@@ -0,0 +1,129 @@
+use serde::Serialize;
+
+use crate::ecosystem::Ecosystem;
+use crate::error::Result;
+use crate::git::{self, RepoStatus};
+use crate::output::{OutputFormat, print_json};
+use crate::workspace::Workspace;
+
+pub struct StatusOptions {
+    /// Show every repository with its ecosystems, not only the ones with changes
+    pub long: bool,
+    pub format: OutputFormat,
+}
+
+#[derive(Serialize)]
+struct RepoEntry {
+    path: String,
+    branch: String,
+    has_changes: bool,
+    staged_files: usize,
+    modified_files: usize,
+    untracked_files: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    ahead: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    behind: Option<usize>,
+    ecosystems: Vec<Ecosystem>,
+    manifests: Vec<&'static str>,
+}
+
+pub async fn status(workspace: &Workspace, options: &StatusOptions) -> Result<()> {
+    let statuses = git::get_all_repos_status(&workspace.path).await?;
+
+    if options.format == OutputFormat::Json {
+        let entries: Vec<_> = statuses.into_iter().map(repo_entry).collect();
+        return print_json(&entries);
+    }
+
+    // Count repositories with and without changes
+    let repos_with_changes: Vec<_> = statuses.iter().filter(|s| s.has_changes).collect();
+    let total_repos = statuses.len();
+    let clean_repos = total_repos - repos_with_changes.len();
+
+    // Print summary
+    println!("Workspace status:");
+    println!("  {} repositories total", total_repos);
+    println!(
+        "  {} clean, {} with changes",
+        clean_repos,
+        repos_with_changes.len()
+    );
+    println!();
+
+    if options.long {
+        println!("Repositories:");
+        println!();
+        for status in &statuses {
+            print_repo(status, true);
+        }
+        return Ok(());
+    }
+
+    // Print details for repos with changes
+    if repos_with_changes.is_empty() {
+        println!("All repositories are clean.");
+    } else {
+        println!("Repositories with changes:");
+        println!();
+
+        for status in repos_with_changes {
+            print_repo(status, false);
+        }
+    }
+    Ok(())
+}
+
+fn print_repo(status: &RepoStatus, long: bool) {
+    println!(
+        "  {} ({})",
+        status.path_relative.to_string_lossy(),
+        status.current_branch
+    );
+
+    if long {
+        let ecosystems: Vec<_> = status.ecosystems.iter().map(|e| e.name()).collect();
+        if ecosystems.is_empty() {
+            println!("    no known ecosystem");
+        } else {
+            println!(
+                "    {} ({})",
+                ecosystems.join(", "),
+                status.manifests.join(", ")
+            );
+        }
+    }
+    if status.staged_files > 0 {
+        println!("    {} file(s) with staged changes", status.staged_files);
+    }
+    if status.modified_files > 0 {
+        println!(
+            "    {} file(s) with unstaged changes",
+            status.modified_files
+        );
+    }
+    if status.untracked_files > 0 {
+        println!("    {} untracked file(s)", status.untracked_files);
+    }
+    if let Some((ahead, behind)) = status.ahead_behind
+        && (ahead > 0 || behind > 0)
+    {
+        println!("    {ahead} commit(s) ahead, {behind} behind upstream");
+    }
+    println!();
+}
+
+fn repo_entry(status: RepoStatus) -> RepoEntry {
+    RepoEntry {
+        path: status.path_relative.to_string_lossy().to_string(),
+        branch: status.current_branch,
+        has_changes: status.has_changes,
+        staged_files: status.staged_files,
+        modified_files: status.modified_files,
+        untracked_files: status.untracked_files,
+        ahead: status.ahead_behind.map(|(ahead, _)| ahead),
+        behind: status.ahead_behind.map(|(_, behind)| behind),
+        ecosystems: status.ecosystems,
+        manifests: status.manifests,
+    }
+}
@@ -0,0 +1,413 @@
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+
+use ratatui::DefaultTerminal;
+use ratatui::Frame;
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind};
+use ratatui::layout::{Constraint, Layout};
+use ratatui::style::{Modifier, Style, Stylize};
+use ratatui::text::Line;
+use ratatui::widgets::{Row, Table, TableState};
+use tokio::sync::{Mutex, Notify, mpsc};
+
+use crate::error::{NutError, Result};
+use crate::git::{self, GitCommand, RepoStatus};
+use crate::workspace::Workspace;
+
+/// How often repository status is refreshed in the background
+const REFRESH_INTERVAL: Duration = Duration::from_secs(2);
+
+const HELP: &str =
+    "q quit  / filter  enter shell  d diff  a stage all  c commit  f fetch  F fetch all";
+
+/// Things the event loop does on behalf of a key press
+enum Action {
+    Quit,
+    Shell(PathBuf),
+    Diff(PathBuf),
+    Stage(PathBuf),
+    Commit(PathBuf),
+    Fetch(Vec<PathBuf>),
+}
+
+struct App {
+    title: String,
+    statuses: Vec<RepoStatus>,
+    table: TableState,
+    filter: String,
+    filtering: bool,
+    message: Option<String>,
+}
+
+impl App {
+    /// Repositories matching the filter, by path or branch
+    fn visible(&self) -> Vec<&RepoStatus> {
+        let filter = self.filter.to_lowercase();
+        self.statuses
+            .iter()
+            .filter(|s| {
+                s.path_relative
+                    .to_string_lossy()
+                    .to_lowercase()
+                    .contains(&filter)
+                    || s.current_branch.to_lowercase().contains(&filter)
+            })
+            .collect()
+    }
+
+    fn selected(&self) -> Option<PathBuf> {
+        let visible = self.visible();
+        let status = visible.get(self.table.selected()?)?;
+        Some(PathBuf::from(&status.path_relative))
+    }
+
+    /// Keep the selection within the visible repositories
+    fn clamp_selection(&mut self) {
+        let len = self.visible().len();
+        match self.table.selected() {
+            _ if len == 0 => self.table.select(None),
+            Some(i) if i >= len => self.table.select(Some(len - 1)),
+            None => self.table.select(Some(0)),
+            _ => {}
+        }
+    }
+
+    fn handle_key(&mut self, key: KeyEvent) -> Option<Action> {
+        if self.filtering {
+            match key.code {
+                KeyCode::Enter => self.filtering = false,
+                KeyCode::Esc => {
+                    self.filtering = false;
+                    self.filter.clear();
+                }
+                KeyCode::Backspace => {
+                    self.filter.pop();
+                }
+                KeyCode::Char(c) => self.filter.push(c),
+                _ => {}
+            }
+            self.clamp_selection();
+            return None;
+        }
+
+        match key.code {
+            KeyCode::Char('q') | KeyCode::Esc => return Some(Action::Quit),
+            KeyCode::Down | KeyCode::Char('j') => self.table.select_next(),
+            KeyCode::Up | KeyCode::Char('k') => self.table.select_previous(),
+            KeyCode::Home | KeyCode::Char('g') => self.table.select_first(),
+            KeyCode::End | KeyCode::Char('G') => self.table.select_last(),
+            KeyCode::Char('/') => self.filtering = true,
+            KeyCode::Enter => return self.selected().map(Action::Shell),
+            KeyCode::Char('d') => return self.selected().map(Action::Diff),
+            KeyCode::Char('a') => return self.selected().map(Action::Stage),
+            KeyCode::Char('c') => return self.selected().map(Action::Commit),
+            KeyCode::Char('f') => return self.selected().map(|r| Action::Fetch(vec![r])),
+            KeyCode::Char('F') => {
+                let repos = self
+                    .visible()
+                    .iter()
+                    .map(|s| PathBuf::from(&s.path_relative))
+                    .collect();
+                return Some(Action::Fetch(repos));
+            }
+            _ => {}
+        }
+        self.clamp_selection();
+        None
+    }
+
+    fn draw(&mut self, frame: &mut Frame) {
+        let [header, body, footer] = Layout::vertical([
+            Constraint::Length(1),
+            Constraint::Min(0),
+            Constraint::Length(1),
+        ])
+        .areas(frame.area());
+
+        let visible = self.visible();
+        let with_changes = visible.iter().filter(|s| s.has_changes).count();
+        let mut title = format!(
+            "{}: {} repositories, {} with changes",
+            self.title,
+            visible.len(),
+            with_changes
+        );
+        if !self.filter.is_empty() {
+            title.push_str(&format!("  (filter: {})", self.filter));
+        }
+
+        let rows: Vec<Row> = visible
+            .iter()
+            .map(|s| {
+                let row = Row::new([
+                    s.path_relative.to_string_lossy().to_string(),
+                    s.current_branch.clone(),
+                    describe_changes(s),
+                    describe_upstream(s),
+                ]);
+                if s.has_changes { row.yellow() } else { row }
+            })
+            .collect();
+        let table = Table::new(
+            rows,
+            [
+                Constraint::Fill(3),
+                Constraint::Fill(2),
+                Constraint::Length(16),
+                Constraint::Length(10),
+            ],
+        )
+        .header(
+            Row::new(["Repository", "Branch", "Changes", "Upstream"])
+                .style(Style::new().add_modifier(Modifier::BOLD)),
+        )
+        .row_highlight_style(Style::new().add_modifier(Modifier::REVERSED));
+
+        let footer_text = if self.filtering {
+            format!("/{}", self.filter)
+        } else {
+            self.message.clone().unwrap_or_else(|| HELP.to_string())
+        };
+
+        frame.render_widget(Line::from(title).bold(), header);
+        frame.render_stateful_widget(table, body, &mut self.table);
+        frame.render_widget(Line::from(footer_text).dim(), footer);
+    }
+}
+
+/// Interactive overview of all repositories in a workspace
+pub async fn run(workspace: &Workspace) -> Result<()> {
+    let title = match workspace.name().await {
+        Some(name) => format!("{name} ({})", workspace.id),
+        None => workspace.id.to_string(),
+    };
+    let mut app = App {
+        title,
+        statuses: git::get_all_repos_status(&workspace.path).await?,
+        table: TableState::default(),
+        filter: String::new(),
+        filtering: false,
+        message: None,
+    };
+    app.clamp_selection();
+
+    let (status_tx, status_rx) = mpsc::unbounded_channel();
+    let refresh = Arc::new(Notify::new());
+    let pause = Arc::new(Mutex::new(()));
+    let refresher = tokio::spawn(refresh_statuses(
+        workspace.path.clone(),
+        status_tx,
+        refresh.clone(),
+        pause.clone(),
+    ));
+
+    let mut terminal = ratatui::try_init().map_err(|e| NutError::TerminalFailed { source: e })?;
+    let result = event_loop(
+        &mut terminal,
+        &mut app,
+        workspace,
+        status_rx,
+        refresh,
+        &pause,
+    )
+    .await;
+    ratatui::restore();
+    refresher.abort();
+
+    result
+}
+
+async fn event_loop(
+    terminal: &mut DefaultTerminal,
+    app: &mut App,
+    workspace: &Workspace,
+    mut status_rx: mpsc::UnboundedReceiver<Vec<RepoStatus>>,
+    refresh: Arc<Notify>,
+    pause: &Mutex<()>,
+) -> Result<()> {
+    let (message_tx, mut message_rx) = mpsc::unbounded_channel();
+
+    loop {
+        while let Ok(statuses) = status_rx.try_recv() {
+            app.statuses = statuses;
+            app.clamp_selection();
+        }
+        while let Ok(message) = message_rx.try_recv() {
+            app.message = Some(message);
+        }
+
+        terminal
+            .draw(|frame| app.draw(frame))
+            .map_err(|e| NutError::TerminalFailed { source: e })?;
+
+        // crossterm only offers blocking reads
+        let event = tokio::task::block_in_place(|| {
+            if event::poll(Duration::from_millis(200))? {
+                event::read().map(Some)
+            } else {
+                Ok(None)
+            }
+        })
+        .map_err(|e| NutError::TerminalFailed { source: e })?;
+
+        let Some(Event::Key(key)) = event else {
+            continue;
+        };
+        if key.kind != KeyEventKind::Press {
+            continue;
+        }
+        app.message = None;
+
+        let Some(action) = app.handle_key(key) else {
+            continue;
+        };
+        match action {
+            Action::Quit => return Ok(()),
+            Action::Shell(repo) => {
+                let shell = std::env::var("SHELL").unwrap_or("/bin/sh".to_string());
+                let mut command = tokio::process::Command::new(shell);
+                command.current_dir(workspace.path.join(&repo));
+                app.message = suspend(terminal, &mut command, pause).await?;
+            }
+            Action::Diff(repo) => {
+                let mut command = tokio::process::Command::new("git");
+                command
+                    .args(["diff", "HEAD"])
+                    .current_dir(workspace.path.join(&repo));
+                app.message = suspend(terminal, &mut command, pause).await?;
+            }
+            Action::Commit(repo) => {
+                let mut command = tokio::process::Command::new("git");
+                command
+                    .arg("commit")
+                    .current_dir(workspace.path.join(&repo));
+                app.message = suspend(terminal, &mut command, pause).await?;
+            }
+            Action::Stage(repo) => {
+                let _paused = pause.lock().await;
+                let result = GitCommand::new(&workspace.path.join(&repo))
+                    .args(&["add", "--all"])
+                    .checked_output()
+                    .await;
+                app.message = Some(match result {
+                    Ok(_) => format!("{}: staged all changes", repo.display()),
+                    Err(e) => format!("{}: {e}", repo.display()),
+                });
+            }
+            Action::Fetch(repos) => {
+                app.message = Some(format!("Fetching {} repositories...", repos.len()));
+                tokio::spawn(fetch(
+                    workspace.path.clone(),
+                    repos,
+                    message_tx.clone(),
+                    refresh.clone(),
+                ));
+            }
+        }
+        refresh.notify_one();
+    }
+}
+
+/// Leave the terminal UI while running an interactive command. Returns a
+/// message to show if the command failed.
+///
+/// The status refresh is paused meanwhile, so it doesn't get in the way of git
+/// commands run by the user.
+async fn suspend(
+    terminal: &mut DefaultTerminal,
+    command: &mut tokio::process::Command,
+    pause: &Mutex<()>,
+) -> Result<Option<String>> {
+    let _paused = pause.lock().await;
+    ratatui::restore();
+    let status = command.status().await;
+    *terminal = ratatui::try_init().map_err(|e| NutError::TerminalFailed { source: e })?;
+
+    Ok(match status {
+        Ok(status) if status.success() => None,
+        Ok(status) => Some(crate::apply::describe_exit_status(&status)),
+        Err(e) => Some(format!("Failed to run command: {e}")),
+    })
+}
+
+async fn refresh_statuses(
+    workspace_path: PathBuf,
+    status_tx: mpsc::UnboundedSender<Vec<RepoStatus>>,
+    refresh: Arc<Notify>,
+    pause: Arc<Mutex<()>>,
+) {
+    loop {
+        tokio::select! {
+            _ = tokio::time::sleep(REFRESH_INTERVAL) => {}
+            _ = refresh.notified() => {}
+        }
+        let statuses = {
+            let _paused = pause.lock().await;
+            git::get_all_repos_status(&workspace_path).await
+        };
+        if let Ok(statuses) = statuses
+            && status_tx.send(statuses).is_err()
+        {
+            return;
+        }
+    }
+}
+
+async fn fetch(
+    workspace_path: PathBuf,
+    repos: Vec<PathBuf>,
+    message_tx: mpsc::UnboundedSender<String>,
+    refresh: Arc<Notify>,
+) {
+    let futures = repos.iter().map(|repo| fetch_repo(&workspace_path, repo));
+    let failed = futures_util::future::join_all(futures)
+        .await
+        .into_iter()
+        .filter(|ok| !ok)
+        .count();
+
+    let message = if failed == 0 {
+        format!("Fetched {} repositories", repos.len())
+    } else {
+        format!(
+            "Fetched {} repositories, {failed} failed",
+            repos.len() - failed
+        )
+    };
+    message_tx.send(message).ok();
+    refresh.notify_one();
+}
+
+async fn fetch_repo(workspace_path: &Path, repo: &Path) -> bool {
+    GitCommand::new(&workspace_path.join(repo))
+        .args(&["fetch", "--quiet"])
+        .checked_output()
+        .await
+        .is_ok()
+}
+
+fn describe_changes(status: &RepoStatus) -> String {
+    if !status.has_changes {
+        return "clean".to_string();
+    }
+    let mut parts = Vec::new();
+    if status.staged_files > 0 {
+        parts.push(format!("+{}", status.staged_files));
+    }
+    if status.modified_files > 0 {
+        parts.push(format!("~{}", status.modified_files));
+    }
+    if status.untracked_files > 0 {
+        parts.push(format!("?{}", status.untracked_files));
+    }
+    parts.join(" ")
+}
+
+fn describe_upstream(status: &RepoStatus) -> String {
+    match status.ahead_behind {
+        Some((0, 0)) => "up to date".to_string(),
+        Some((ahead, behind)) => format!("↑{ahead} ↓{behind}"),
+        None => "-".to_string(),
+    }
+}
@@ -4,8 +4,8 @@ use crate::error::{NutError, Result};
 use std::path::PathBuf;
 use ulid::Ulid;
 
+#[derive(Clone)]
 pub struct Workspace {
-    #[allow(dead_code)]
     pub id: Ulid,
     pub path: PathBuf,
 }
@@ -13,19 +13,227 @@ pub struct Workspace {
 impl Workspace {
     /// Get workspace from explicit ID or infer from current directory
     pub async fn resolve(workspace_arg: &Option<String>) -> Result<Self> {
-        let ulid = match workspace_arg {
-            Some(id) => id.parse().map_err(|e| NutError::InvalidWorkspaceId {
-                id: id.clone(),
-                source: e,
-            })?,
-            None => enter::get_entered_workspace().await?,
+        match workspace_arg {
+            Some(id) => Self::find(id).await,
+            None => Self::from_id(enter::get_entered_workspace().await?).await,
+        }
+    }
+
+    /// Find a workspace by ULID, name or unique ULID prefix
+    pub async fn find(id: &str) -> Result<Self> {
+        let parse_error = match id.parse::<Ulid>() {
+            Ok(ulid) => {
+                let workspace = Self::from_id(ulid).await?;
+                if !tokio::fs::try_exists(&workspace.path)
+                    .await
+                    .unwrap_or(false)
+                {
+                    return Err(NutError::WorkspaceNotFound { id: id.to_string() });
+                }
+                return Ok(workspace);
+            }
+            Err(e) => e,
         };
 
-        let workspace_dir = dirs::get_data_local_dir().await?.join(ulid.to_string());
+        let workspaces = Self::all().await?;
+
+        for workspace in &workspaces {
+            if workspace.name().await.as_deref() == Some(id) {
+                return Ok(workspace.clone());
+            }
+        }
+
+        let prefix = id.to_uppercase();
+        let mut matches: Vec<Workspace> = workspaces
+            .into_iter()
+            .filter(|w| !prefix.is_empty() && w.id.to_string().starts_with(&prefix))
+            .collect();
+
+        match matches.len() {
+            0 => Err(NutError::InvalidWorkspaceId {
+                id: id.to_string(),
+                source: parse_error,
+            }),
+            1 => Ok(matches.remove(0)),
+            _ => Err(NutError::AmbiguousWorkspaceId {
+                id: id.to_string(),
+                matches: matches
+                    .iter()
+                    .map(|w| w.id.to_string())
+                    .collect::<Vec<_>>()
+                    .join(", "),
+            }),
+        }
+    }
+
+    /// Create a new workspace with a fresh ID
+    pub async fn create(description: &str) -> Result<Self> {
+        let workspace = Self::from_id(Ulid::new()).await?;
+
+        let metadata_dir = workspace.metadata_dir();
+        tokio::fs::create_dir_all(&metadata_dir)
+            .await
+            .map_err(|e| NutError::CreateDirectoryFailed {
+                path: metadata_dir,
+                source: e,
+            })?;
+        workspace.set_description(description).await?;
+
+        Ok(workspace)
+    }
+
+    pub async fn from_id(id: Ulid) -> Result<Self> {
+        let path = dirs::get_data_local_dir().await?.join(id.to_string());
+        Ok(Workspace { id, path })
+    }
+
+    /// All workspaces in the data directory, in no particular order
+    pub async fn all() -> Result<Vec<Self>> {
+        let data_local_dir = dirs::get_data_local_dir().await?;
+        let mut entries = tokio::fs::read_dir(&data_local_dir).await.map_err(|e| {
+            NutError::ReadDirectoryFailed {
+                path: data_local_dir.clone(),
+                source: e,
+            }
+        })?;
+
+        let mut workspaces = Vec::new();
+        loop {
+            let entry = entries
+                .next_entry()
+                .await
+                .map_err(|e| NutError::ReadDirectoryFailed {
+                    path: data_local_dir.clone(),
+                    source: e,
+                })?;
+            let Some(entry) = entry else {
+                break;
+            };
+            if !entry.path().is_dir() {
+                continue;
+            }
+            if let Some(id) = entry
+                .file_name()
+                .to_str()
+                .and_then(|s| Ulid::from_string(s).ok())
+            {
+                workspaces.push(Workspace {
+                    id,
+                    path: entry.path(),
+                });
+            }
+        }
+
+        Ok(workspaces)
+    }
+
+    pub fn metadata_dir(&self) -> PathBuf {
+        self.path.join(".nut")
+    }
+
+    pub async fn description(&self) -> Option<String> {
+        self.read_metadata("description").await
+    }
 
-        Ok(Workspace {
-            id: ulid,
-            path: workspace_dir,
-        })
+    pub async fn set_description(&self, description: &str) -> Result<()> {
+        self.write_metadata("description", description).await
     }
+
+    pub async fn name(&self) -> Option<String> {
+        self.read_metadata("name")
+            .await
+            .map(|n| n.trim().to_string())
+            .filter(|n| !n.is_empty())
+    }
+
+    /// Set the workspace name, making sure it is valid and not used by another workspace
+    pub async fn set_name(&self, name: &str) -> Result<()> {
+        Self::validate_name(name, Some(self.id)).await?;
+        self.write_metadata("name", name).await
+    }
+
+    /// Check that a name can be used to address a workspace
+    pub async fn validate_name(name: &str, own_id: Option<Ulid>) -> Result<()> {
+        if !is_valid_name(name) || name.parse::<Ulid>().is_ok() {
+            return Err(NutError::InvalidWorkspaceName {
+                name: name.to_string(),
+            });
+        }
+
+        for workspace in Self::all().await? {
+            if Some(workspace.id) != own_id && workspace.name().await.as_deref() == Some(name) {
+                return Err(NutError::WorkspaceNameTaken {
+                    name: name.to_string(),
+                    id: workspace.id.to_string(),
+                });
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Tags are stored one per line in `.nut/tags`
+    pub async fn tags(&self) -> Vec<String> {
+        self.read_metadata("tags")
+            .await
+            .unwrap_or_default()
+            .lines()
+            .map(|t| t.trim().to_string())
+            .filter(|t| !t.is_empty())
+            .collect()
+    }
+
+    pub async fn set_tags(&self, tags: &[String]) -> Result<()> {
+        for tag in tags {
+            if tag.is_empty() || tag.contains(|c: char| c.is_whitespace() || c == ',') {
+                return Err(NutError::InvalidTag { tag: tag.clone() });
+            }
+        }
+        let mut contents = tags.join("\n");
+        contents.push('\n');
+        self.write_metadata("tags", &contents).await
+    }
+
+    /// A workspace is archived if the `.nut/archived` marker file exists
+    pub async fn is_archived(&self) -> bool {
+        tokio::fs::try_exists(self.metadata_dir().join("archived"))
+            .await
+            .unwrap_or(false)
+    }
+
+    pub async fn set_archived(&self, archived: bool) -> Result<()> {
+        let path = self.metadata_dir().join("archived");
+        if archived {
+            self.write_metadata("archived", "").await
+        } else if tokio::fs::try_exists(&path).await.unwrap_or(false) {
+            tokio::fs::remove_file(&path)
+                .await
+                .map_err(|e| NutError::WriteFileFailed { path, source: e })
+        } else {
+            Ok(())
+        }
+    }
+
+    async fn read_metadata(&self, file: &str) -> Option<String> {
+        tokio::fs::read_to_string(self.metadata_dir().join(file))
+            .await
+            .ok()
+    }
+
+    async fn write_metadata(&self, file: &str, contents: &str) -> Result<()> {
+        let path = self.metadata_dir().join(file);
+        tokio::fs::write(&path, contents)
+            .await
+            .map_err(|e| NutError::WriteFileFailed { path, source: e })
+    }
+}
+
+/// Names start with a letter or digit and only contain letters, digits, '-', '_' and '.'
+pub fn is_valid_name(name: &str) -> bool {
+    name.chars()
+        .next()
+        .is_some_and(|c| c.is_ascii_alphanumeric())
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'))
 }
@@ -93,6 +93,33 @@ impl TestEnv {
             .expect("Failed to execute nut command")
     }
 
+    /// Execute nut command, writing `input` to its stdin
+    fn run_nut_with_input(
+        &self,
+        args: &[&str],
+        workspace_id: Option<ulid::Ulid>,
+        input: &str,
+    ) -> std::process::Output {
+        use std::io::Write;
+
+        let mut child = Command::new(Self::nut_binary())
+            .args(args)
+            .current_dir(self.nut_working_directory(workspace_id))
+            .env("HOME", &self.temp_dir)
+            .stdin(std::process::Stdio::piped())
+            .stdout(std::process::Stdio::piped())
+            .stderr(std::process::Stdio::piped())
+            .spawn()
+            .expect("Failed to execute nut command");
+        child
+            .stdin
+            .take()
+            .unwrap()
+            .write_all(input.as_bytes())
+            .unwrap();
+        child.wait_with_output().unwrap()
+    }
+
     /// Get the data directory path for this test environment
     fn get_data_dir(&self) -> PathBuf {
         // Return the workspace directory that was configured during setup
@@ -451,6 +478,23 @@ fn test_error_invalid_workspace_id() {
     );
 }
 
+#[test]
+fn test_error_workspace_not_found() {
+    let env = TestEnv::new("error_workspace_not_found");
+
+    let output = env.run_nut(&["rename", "01JABCDEFGHJKMNPQRSTVWXYZ0", "ghost"], None);
+
+    assert!(
+        !output.status.success(),
+        "rename should fail for a workspace that doesn't exist"
+    );
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains("nut::workspace::not_found"),
+        "Error should have correct error code, got:\n{stderr}"
+    );
+}
+
 #[test]
 fn test_error_already_in_workspace() {
     let env = TestEnv::new("error_already_in_workspace");
@@ -842,3 +886,1786 @@ fn test_import_requires_query_or_positional() {
         stderr
     );
 }
+
+#[test]
+fn test_rename_and_resolve_by_name() {
+    let env = TestEnv::new("rename_resolve");
+
+    let workspace = env.create_workspace("Named workspace");
+    env.create_repo(&workspace, "org", "repo");
+
+    let output = env.run_nut(&["rename", &workspace.id.to_string(), "my-campaign"], None);
+    assert!(
+        output.status.success(),
+        "rename should succeed: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+
+    let output = env.run_nut(&["status", "--workspace", "my-campaign"], None);
+    assert!(
+        output.status.success(),
+        "status should accept a workspace name: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        stdout.contains("1 repositories total"),
+        "status should show the named workspace, got:\n{stdout}"
+    );
+
+    let output = env.run_nut(&["list"], None);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        stdout.contains(&format!("{} (my-campaign)", workspace.id)),
+        "list should show the workspace name, got:\n{stdout}"
+    );
+}
+
+#[test]
+fn test_workspace_name_must_be_unique() {
+    let env = TestEnv::new("rename_unique");
+
+    let workspace1 = env.create_workspace("First workspace");
+    let workspace2 = env.create_workspace("Second workspace");
+
+    let output = env.run_nut(&["rename", &workspace1.id.to_string(), "shared"], None);
+    assert!(output.status.success());
+
+    let output = env.run_nut(&["rename", &workspace2.id.to_string(), "shared"], None);
+    assert!(
+        !output.status.success(),
+        "rename should fail when the name is already taken"
+    );
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains("nut::workspace::name_taken"),
+        "Error should have correct error code, got:\n{stderr}"
+    );
+}
+
+#[test]
+fn test_resolve_workspace_by_id_prefix() {
+    let env = TestEnv::new("resolve_prefix");
+
+    let workspace = env.create_workspace("Prefix workspace");
+
+    // With a single workspace, any prefix is unique
+    let prefix = &workspace.id.to_string()[..8].to_lowercase();
+    let output = env.run_nut(&["workspace-dir", "--workspace", prefix], None);
+    assert!(
+        output.status.success(),
+        "workspace-dir should accept a unique ID prefix: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert_eq!(PathBuf::from(stdout.trim()), workspace.path);
+}
+
+#[test]
+fn test_describe_workspace() {
+    let env = TestEnv::new("describe");
+
+    let workspace = env.create_workspace("Old description");
+
+    let output = env.run_nut(
+        &["describe", &workspace.id.to_string(), "New description"],
+        None,
+    );
+    assert!(
+        output.status.success(),
+        "describe should succeed: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+
+    let output = env.run_nut(&["list"], None);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        stdout.contains("New description") && !stdout.contains("Old description"),
+        "list should show the new description, got:\n{stdout}"
+    );
+}
+
+#[test]
+fn test_list_hides_archived_and_filters_by_tag() {
+    let env = TestEnv::new("list_archive_tags");
+
+    let archived = env.create_workspace("Finished campaign");
+    let tagged = env.create_workspace("Terraform campaign");
+    let untagged = env.create_workspace("Other campaign");
+
+    let output = env.run_nut(&["archive", &archived.id.to_string()], None);
+    assert!(output.status.success());
+    let output = env.run_nut(&["tag", &tagged.id.to_string(), "terraform", "infra"], None);
+    assert!(output.status.success());
+
+    let output = env.run_nut(&["list"], None);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        !stdout.contains(&archived.id.to_string()),
+        "list should hide archived workspaces, got:\n{stdout}"
+    );
+    assert!(
+        stdout.contains("Tags: terraform, infra"),
+        "list should show tags, got:\n{stdout}"
+    );
+
+    let output = env.run_nut(&["list", "--archived"], None);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        stdout.contains(&archived.id.to_string()),
+        "list --archived should show archived workspaces, got:\n{stdout}"
+    );
+
+    let output = env.run_nut(&["list", "--tag", "terraform"], None);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        stdout.contains(&tagged.id.to_string()) && !stdout.contains(&untagged.id.to_string()),
+        "list --tag should only show tagged workspaces, got:\n{stdout}"
+    );
+
+    let output = env.run_nut(&["unarchive", &archived.id.to_string()], None);
+    assert!(output.status.success());
+    let output = env.run_nut(&["list"], None);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        stdout.contains(&archived.id.to_string()),
+        "unarchived workspace should be listed again, got:\n{stdout}"
+    );
+}
+
+#[test]
+fn test_list_columns_json_and_limit() {
+    let env = TestEnv::new("list_columns_json");
+
+    let older = env.create_workspace("Older workspace");
+    env.create_repo(&older, "org", "repo-1");
+    let repo = env.create_repo(&older, "org", "repo-2");
+    fs::write(repo.path.join("dirty.txt"), "dirty\n").unwrap();
+    std::thread::sleep(std::time::Duration::from_millis(10));
+    let newer = env.create_workspace("Newer workspace");
+
+    let output = env.run_nut(
+        &[
+            "list",
+            "--columns",
+            "repos,dirty,activity",
+            "--format",
+            "json",
+        ],
+        None,
+    );
+    assert!(
+        output.status.success(),
+        "list should succeed: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    let entries: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    let entries = entries.as_array().unwrap();
+    assert_eq!(entries.len(), 2);
+    assert_eq!(entries[0]["id"], newer.id.to_string());
+    assert_eq!(entries[1]["id"], older.id.to_string());
+    assert_eq!(entries[1]["repos"], 2);
+    assert_eq!(entries[1]["dirty_repos"], 1);
+    assert!(entries[1]["last_activity"].is_string());
+    assert!(entries[1].get("disk_usage").is_none());
+
+    let output = env.run_nut(&["list", "--sort", "repos", "--limit", "1"], None);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        stdout.contains(&older.id.to_string()) && !stdout.contains(&newer.id.to_string()),
+        "list should sort by repository count and apply the limit, got:\n{stdout}"
+    );
+}
+
+fn git(dir: &std::path::Path, args: &[&str]) -> String {
+    let output = Command::new("git")
+        .args(args)
+        .current_dir(dir)
+        .output()
+        .expect("Failed to run git");
+    assert!(
+        output.status.success(),
+        "git {} failed: {}",
+        args.join(" "),
+        String::from_utf8_lossy(&output.stderr)
+    );
+    String::from_utf8_lossy(&output.stdout).trim().to_string()
+}
+
+#[test]
+fn test_export_and_import_bundle() {
+    let env = TestEnv::new("export_import_bundle");
+
+    // An upstream repository, mirrored into the cache as if it had been imported before
+    let upstream = env.temp_dir.join("upstream/org/repo");
+    fs::create_dir_all(&upstream).unwrap();
+    git(&upstream, &["init", "--initial-branch", "main"]);
+    fs::write(upstream.join("README.md"), "# Upstream\n").unwrap();
+    git(&upstream, &["add", "."]);
+    git(
+        &upstream,
+        &[
+            "-c",
+            "user.email=test@example.com",
+            "-c",
+            "user.name=Test User",
+            "commit",
+            "-m",
+            "Initial commit",
+        ],
+    );
+    let cache_dir = env.get_cache_dir().join("github/org");
+    fs::create_dir_all(&cache_dir).unwrap();
+    git(
+        &cache_dir,
+        &["clone", "--mirror", upstream.to_str().unwrap(), "repo"],
+    );
+
+    // A workspace with unpushed work
+    let workspace = env.create_workspace("Half-finished migration");
+    let repo = workspace.path.join("org/repo");
+    fs::create_dir_all(repo.parent().unwrap()).unwrap();
+    git(
+        repo.parent().unwrap(),
+        &["clone", upstream.to_str().unwrap(), "repo"],
+    );
+    git(&repo, &["config", "user.email", "test@example.com"]);
+    git(&repo, &["config", "user.name", "Test User"]);
+    git(&repo, &["checkout", "-b", "migration"]);
+    fs::write(repo.join("committed.txt"), "committed\n").unwrap();
+    git(&repo, &["add", "."]);
+    git(&repo, &["commit", "-m", "Local commit"]);
+    let local_commit = git(&repo, &["rev-parse", "HEAD"]);
+    fs::write(repo.join("staged.txt"), "staged\n").unwrap();
+    git(&repo, &["add", "staged.txt"]);
+    fs::write(repo.join("README.md"), "# Modified\n").unwrap();
+    fs::write(repo.join("untracked.txt"), "untracked\n").unwrap();
+    #[cfg(unix)]
+    std::os::unix::fs::symlink("untracked.txt", repo.join("link.txt")).unwrap();
+
+    let bundle = env.temp_dir.join("campaign.tar.zst");
+    let output = env.run_nut(
+        &[
+            "export",
+            &workspace.id.to_string(),
+            "--output",
+            bundle.to_str().unwrap(),
+        ],
+        None,
+    );
+    assert!(
+        output.status.success(),
+        "export should succeed: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+
+    let output = env.run_nut(&["import-bundle", bundle.to_str().unwrap()], None);
+    assert!(
+        output.status.success(),
+        "import-bundle should succeed: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let imported_id = stdout
+        .lines()
+        .find_map(|l| l.strip_prefix("Imported workspace "))
+        .expect("import-bundle should print the new workspace ID");
+
+    let imported = env.get_data_dir().join(imported_id).join("org/repo");
+    assert_eq!(git(&imported, &["branch", "--show-current"]), "migration");
+    assert_eq!(git(&imported, &["rev-parse", "HEAD"]), local_commit);
+    assert_eq!(
+        git(&imported, &["diff", "--cached", "--name-only"]),
+        "staged.txt"
+    );
+    assert_eq!(git(&imported, &["diff", "--name-only"]), "README.md");
+    assert_eq!(
+        fs::read_to_string(imported.join("untracked.txt")).unwrap(),
+        "untracked\n"
+    );
+    #[cfg(unix)]
+    assert_eq!(
+        fs::read_link(imported.join("link.txt")).unwrap(),
+        PathBuf::from("untracked.txt"),
+        "symbolic links should be restored as links"
+    );
+    assert_eq!(
+        fs::read_to_string(
+            env.get_data_dir()
+                .join(imported_id)
+                .join(".nut/description")
+        )
+        .unwrap(),
+        "Half-finished migration"
+    );
+}
+
+#[test]
+fn test_import_bundle_rejects_unsafe_paths() {
+    let env = TestEnv::new("import_bundle_unsafe_paths");
+
+    let write_bundle = |name: &str, repo_path: &str, untracked: &str| {
+        let manifest = format!(
+            r#"{{"version": 1, "name": null, "description": "Bundle", "tags": [], "repos": [{{
+                "path": "{repo_path}", "default_branch": null, "default_branch_commit": null,
+                "head_branch": null, "head_commit": "0000000000000000000000000000000000000000",
+                "branches": {{}}, "upstreams": {{}}, "has_bundle": false,
+                "has_staged_patch": false, "has_unstaged_patch": false,
+                "untracked_files": ["{untracked}"]
+            }}]}}"#
+        );
+        let bundle = env.temp_dir.join(name);
+        let file = fs::File::create(&bundle).unwrap();
+        let mut builder = tar::Builder::new(zstd::Encoder::new(file, 0).unwrap());
+        let mut header = tar::Header::new_gnu();
+        header.set_size(manifest.len() as u64);
+        header.set_mode(0o644);
+        header.set_cksum();
+        builder
+            .append_data(&mut header, "manifest.json", manifest.as_bytes())
+            .unwrap();
+        builder.into_inner().unwrap().finish().unwrap();
+        bundle
+    };
+    let workspaces = || {
+        fs::read_dir(env.get_data_dir())
+            .map(|entries| entries.count())
+            .unwrap_or(0)
+    };
+
+    for (repo_path, untracked) in [
+        ("../escape", "file.txt"),
+        ("/tmp/escape", "file.txt"),
+        ("org/repo", "../../../escape.txt"),
+    ] {
+        let bundle = write_bundle("unsafe.tar.zst", repo_path, untracked);
+        let output = env.run_nut(&["import-bundle", bundle.to_str().unwrap()], None);
+        assert!(!output.status.success());
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        assert!(
+            stderr.contains("is not inside the workspace"),
+            "'{repo_path}' and '{untracked}' should be rejected, got:\n{stderr}"
+        );
+    }
+    assert_eq!(workspaces(), 0, "no workspace should be created");
+
+    // A workspace that cannot be restored is removed again
+    let bundle = write_bundle("uncached.tar.zst", "org/repo", "file.txt");
+    let output = env.run_nut(&["import-bundle", bundle.to_str().unwrap()], None);
+    assert!(!output.status.success());
+    assert_eq!(workspaces(), 0, "the failed workspace should be removed");
+}
+
+#[test]
+fn test_snapshot_create_and_restore() {
+    let env = TestEnv::new("snapshot_restore");
+    let workspace = env.create_workspace("Snapshot workspace");
+    let repo = env.create_repo(&workspace, "org", "repo");
+
+    git(&repo.path, &["checkout", "-b", "feature"]);
+    let head = git(&repo.path, &["rev-parse", "HEAD"]);
+    fs::write(repo.path.join("staged.txt"), "staged\n").unwrap();
+    git(&repo.path, &["add", "staged.txt"]);
+    fs::write(repo.path.join("README.md"), "# Modified\n").unwrap();
+    fs::write(repo.path.join("untracked.txt"), "untracked\n").unwrap();
+
+    let output = env.run_nut(
+        &["snapshot", "create", "before-rewrite"],
+        Some(workspace.id),
+    );
+    assert!(
+        output.status.success(),
+        "snapshot create should succeed: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+
+    // A scripted mass edit gone wrong
+    git(&repo.path, &["add", "--all"]);
+    git(&repo.path, &["commit", "-m", "Oops"]);
+    git(&repo.path, &["checkout", "-b", "other"]);
+    fs::write(repo.path.join("garbage.txt"), "garbage\n").unwrap();
+
+    let output = env.run_nut(&["snapshot", "list"], Some(workspace.id));
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        stdout.contains("before-rewrite") && stdout.contains("1 repositories"),
+        "snapshot list should show the snapshot, got:\n{stdout}"
+    );
+
+    let output = env.run_nut(
+        &["snapshot", "restore", "before-rewrite"],
+        Some(workspace.id),
+    );
+    assert!(
+        output.status.success(),
+        "snapshot restore should succeed: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+
+    assert_eq!(git(&repo.path, &["branch", "--show-current"]), "feature");
+    assert_eq!(git(&repo.path, &["rev-parse", "HEAD"]), head);
+    assert_eq!(
+        git(&repo.path, &["diff", "--cached", "--name-only"]),
+        "staged.txt"
+    );
+    assert_eq!(git(&repo.path, &["diff", "--name-only"]), "README.md");
+    assert!(repo.path.join("untracked.txt").exists());
+    assert!(!repo.path.join("garbage.txt").exists());
+    assert_eq!(
+        git(
+            &repo.path,
+            &["status", "--porcelain", "--", "untracked.txt"]
+        ),
+        "?? untracked.txt"
+    );
+
+    // The state before the restore was saved and can be brought back
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let backup = stdout
+        .lines()
+        .find_map(|l| l.strip_prefix("Saved the current state as snapshot "))
+        .unwrap_or_else(|| panic!("restore should save the current state, got:\n{stdout}"))
+        .to_string();
+    let output = env.run_nut(&["snapshot", "restore", &backup], Some(workspace.id));
+    assert!(
+        output.status.success(),
+        "restoring the saved state should succeed: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    assert_eq!(git(&repo.path, &["branch", "--show-current"]), "other");
+    assert!(repo.path.join("garbage.txt").exists());
+
+    let output = env.run_nut(
+        &["snapshot", "create", "before-rewrite"],
+        Some(workspace.id),
+    );
+    assert!(
+        !output.status.success(),
+        "creating a snapshot with an existing name should fail"
+    );
+}
+
+#[test]
+fn test_snapshot_create_leaves_nothing_behind_on_failure() {
+    let env = TestEnv::new("snapshot_partial");
+    let workspace = env.create_workspace("Snapshot workspace");
+    let first = env.create_repo(&workspace, "org", "a");
+    let second = env.create_repo(&workspace, "org", "b");
+
+    // A merge conflict leaves unmerged paths, which can't be written as a tree
+    let main = git(&second.path, &["branch", "--show-current"]);
+    git(&second.path, &["checkout", "-b", "conflict"]);
+    fs::write(second.path.join("README.md"), "# Theirs\n").unwrap();
+    git(&second.path, &["commit", "-am", "Theirs"]);
+    git(&second.path, &["checkout", &main]);
+    fs::write(second.path.join("README.md"), "# Ours\n").unwrap();
+    git(&second.path, &["commit", "-am", "Ours"]);
+    let merge = Command::new("git")
+        .args(["merge", "conflict"])
+        .current_dir(&second.path)
+        .output()
+        .expect("Failed to run git");
+    assert!(!merge.status.success(), "the merge should conflict");
+
+    let output = env.run_nut(&["snapshot", "create", "broken"], Some(workspace.id));
+    assert!(
+        !output.status.success(),
+        "snapshot create should fail with unmerged paths"
+    );
+    assert_eq!(
+        git(&first.path, &["for-each-ref", "refs/nut/snapshots"]),
+        "",
+        "the snapshot should not be left in the other repository"
+    );
+}
+
+#[test]
+fn test_log_and_undo_apply() {
+    let env = TestEnv::new("log_undo");
+    let workspace = env.create_workspace("Log workspace");
+    let repo = env.create_repo(&workspace, "org", "repo");
+    let original_branch = git(&repo.path, &["branch", "--show-current"]);
+    let original_commit = git(&repo.path, &["rev-parse", "HEAD"]);
+
+    let output = env.run_nut(
+        &["apply", "--", "git", "checkout", "-b", "campaign"],
+        Some(workspace.id),
+    );
+    assert!(output.status.success());
+    let output = env.run_nut(
+        &[
+            "apply",
+            "--",
+            "git",
+            "commit",
+            "--allow-empty",
+            "-m",
+            "Change",
+        ],
+        Some(workspace.id),
+    );
+    assert!(output.status.success());
+    assert_ne!(git(&repo.path, &["rev-parse", "HEAD"]), original_commit);
+
+    let output = env.run_nut(&["log"], Some(workspace.id));
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        stdout.contains("nut apply -- git checkout -b campaign") && stdout.contains("org/repo: ok"),
+        "log should show the apply operations, got:\n{stdout}"
+    );
+
+    // Undo the commit, then the branch switch
+    let output = env.run_nut(&["undo"], Some(workspace.id));
+    assert!(
+        output.status.success(),
+        "undo should succeed: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    assert_eq!(git(&repo.path, &["branch", "--show-current"]), "campaign");
+    assert_eq!(git(&repo.path, &["rev-parse", "HEAD"]), original_commit);
+
+    let output = env.run_nut(&["undo"], Some(workspace.id));
+    assert!(output.status.success());
+    assert_eq!(
+        git(&repo.path, &["branch", "--show-current"]),
+        original_branch
+    );
+
+    let output = env.run_nut(&["undo"], Some(workspace.id));
+    assert!(
+        !output.status.success(),
+        "undo should fail when there is nothing left to undo"
+    );
+}
+
+#[test]
+fn test_apply_rerun_failed() {
+    let env = TestEnv::new("apply_rerun_failed");
+    let workspace = env.create_workspace("Rerun workspace");
+    let repo_1 = env.create_repo(&workspace, "org", "repo-1");
+    let repo_2 = env.create_repo(&workspace, "org", "repo-2");
+    fs::write(repo_1.path.join("marker"), "").unwrap();
+
+    let command = ["sh", "-c", "test -f marker && echo marker found"];
+    let mut args = vec!["apply", "--"];
+    args.extend(command);
+    let output = env.run_nut(&args, Some(workspace.id));
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        stdout.contains("1 succeeded, 1 failed"),
+        "apply should summarize the run, got:\n{stdout}"
+    );
+    let run_id = stdout
+        .lines()
+        .find_map(|l| l.strip_prefix("Run "))
+        .and_then(|l| l.split(':').next())
+        .unwrap()
+        .to_string();
+
+    // Output of each repository is kept
+    let output = env.run_nut(&["runs", "show", &run_id, "org/repo-1"], Some(workspace.id));
+    assert!(output.status.success());
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "marker found\n");
+
+    fs::write(repo_2.path.join("marker"), "").unwrap();
+    let output = env.run_nut(&["apply", "--rerun-failed", &run_id], Some(workspace.id));
+    assert!(
+        output.status.success(),
+        "rerun should succeed: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        stdout.contains("==> org/repo-2 <==") && !stdout.contains("==> org/repo-1 <=="),
+        "rerun should only run in the failed repository, got:\n{stdout}"
+    );
+    assert!(stdout.contains("1 succeeded, 0 failed"));
+
+    // The latest run has no failures left
+    let output = env.run_nut(&["apply", "--rerun-failed"], Some(workspace.id));
+    assert!(output.status.success());
+    assert!(String::from_utf8_lossy(&output.stdout).contains("No failed repositories"));
+}
+
+#[test]
+#[cfg(unix)]
+fn test_apply_recipe() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let env = TestEnv::new("apply_recipe");
+    let workspace = env.create_workspace("Recipe workspace");
+    let node = env.create_repo(&workspace, "org", "node");
+    env.create_repo(&workspace, "org", "other");
+    fs::write(node.path.join("package.json"), "{}").unwrap();
+
+    let recipes_dir = env.temp_dir.join("recipes");
+    fs::create_dir_all(&recipes_dir).unwrap();
+    let recipe = recipes_dir.join("touch-node.sh");
+    fs::write(
+        &recipe,
+        "#!/bin/sh\n\
+         # nut: description: Mark node projects\n\
+         # nut: requires: sh\n\
+         # nut: applies-if: test -f package.json\n\
+         touch touched\n",
+    )
+    .unwrap();
+    fs::set_permissions(&recipe, fs::Permissions::from_mode(0o755)).unwrap();
+
+    // Recipes need a configured directory
+    let output = env.run_nut(&["recipes", "list"], None);
+    assert!(!output.status.success());
+
+    let output = env.run_nut(
+        &["config", "--recipes-dir", recipes_dir.to_str().unwrap()],
+        None,
+    );
+    assert!(output.status.success());
+
+    let output = env.run_nut(&["recipes", "list"], None);
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        stdout.contains("touch-node\n  Mark node projects\n  Requires: sh\n"),
+        "recipes list should show the header, got:\n{stdout}"
+    );
+
+    let output = env.run_nut(&["apply", "--recipe", "touch-node"], Some(workspace.id));
+    assert!(
+        output.status.success(),
+        "apply with recipe should succeed: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        stdout.contains("1 succeeded, 0 failed, 1 skipped"),
+        "repositories where the recipe does not apply should be skipped, got:\n{stdout}"
+    );
+    assert!(node.path.join("touched").exists());
+    assert!(
+        !workspace
+            .path
+            .join("org")
+            .join("other")
+            .join("touched")
+            .exists()
+    );
+
+    let output = env.run_nut(&["apply", "--recipe", "missing"], Some(workspace.id));
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("Recipe not found"));
+}
+
+#[test]
+fn test_status_shows_ahead_of_upstream() {
+    let env = TestEnv::new("status_ahead");
+    let workspace = env.create_workspace("Ahead workspace");
+    let repo = env.create_repo(&workspace, "org", "repo");
+
+    // Track a local branch, then commit on top of it
+    git(&repo.path, &["branch", "base"]);
+    git(&repo.path, &["branch", "--set-upstream-to=base"]);
+    git(&repo.path, &["commit", "--allow-empty", "-m", "Ahead"]);
+    fs::write(repo.path.join("README.md"), "# Changed\n").unwrap();
+
+    let output = env.run_nut(&["status"], Some(workspace.id));
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        stdout.contains("1 commit(s) ahead, 0 behind upstream"),
+        "status should show commits ahead of upstream, got:\n{stdout}"
+    );
+}
+
+#[test]
+fn test_diff_prefixes_and_groups_repositories() {
+    let env = TestEnv::new("diff");
+    let workspace = env.create_workspace("Diff workspace");
+    for name in ["repo-1", "repo-2", "repo-3"] {
+        let repo = env.create_repo(&workspace, "org", name);
+        let content = if name == "repo-3" {
+            "# Outlier\n"
+        } else {
+            "# Changed\n"
+        };
+        fs::write(repo.path.join("README.md"), content).unwrap();
+    }
+
+    let output = env.run_nut(&["diff"], Some(workspace.id));
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        stdout.contains("diff --git a/org/repo-1/README.md b/org/repo-1/README.md")
+            && stdout.contains("+# Outlier"),
+        "diff should prefix paths with the repository, got:\n{stdout}"
+    );
+
+    let output = env.run_nut(&["diff", "--group"], Some(workspace.id));
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let same = stdout.find("==> same changes in 2 repositories <==");
+    let outlier = stdout.find("==> org/repo-3 <==");
+    assert!(
+        same.is_some() && outlier.is_some() && same < outlier,
+        "identical diffs should be grouped before outliers, got:\n{stdout}"
+    );
+
+    let output = env.run_nut(&["diff", "--staged"], Some(workspace.id));
+    assert!(output.status.success());
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "No changes\n");
+}
+
+#[test]
+fn test_commit_skips_clean_repositories() {
+    let env = TestEnv::new("commit");
+    let workspace = env.create_workspace("Commit workspace");
+    let changed = env.create_repo(&workspace, "org", "changed");
+    let clean = env.create_repo(&workspace, "org", "clean");
+    let clean_commit = git(&clean.path, &["rev-parse", "HEAD"]);
+    fs::write(changed.path.join("new.txt"), "new\n").unwrap();
+
+    let output = env.run_nut(
+        &["commit", "--all", "-m", "chore({repo}): add file"],
+        Some(workspace.id),
+    );
+    assert!(
+        output.status.success(),
+        "commit should succeed: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        stdout.contains("org/changed: committed") && !stdout.contains("org/clean"),
+        "only the changed repository should be reported, got:\n{stdout}"
+    );
+    assert!(stdout.contains("Committed in 1 repositories, 0 failed, 1 without changes"));
+
+    assert_eq!(
+        git(&changed.path, &["log", "-1", "--format=%s"]),
+        "chore(org/changed): add file"
+    );
+    assert_eq!(git(&clean.path, &["rev-parse", "HEAD"]), clean_commit);
+
+    // A repository where the commit fails fails the command
+    fs::write(changed.path.join("other.txt"), "other\n").unwrap();
+    fs::create_dir_all(changed.path.join(".git/hooks")).unwrap();
+    let hook = changed.path.join(".git/hooks/pre-commit");
+    fs::write(&hook, "#!/bin/sh\nexit 1\n").unwrap();
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(&hook, fs::Permissions::from_mode(0o755)).unwrap();
+    }
+    let output = env.run_nut(&["commit", "--all", "-m", "other"], Some(workspace.id));
+    assert!(!output.status.success(), "commit should fail");
+    assert!(String::from_utf8_lossy(&output.stdout).contains("1 failed"));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("Commit failed in 1 repositories"));
+}
+
+#[test]
+fn test_push_refuses_default_branch() {
+    let env = TestEnv::new("push");
+    let workspace = env.create_workspace("Push workspace");
+    let feature = env.create_repo(&workspace, "org", "feature");
+    let main = env.create_repo(&workspace, "org", "main");
+
+    // Give every repository a bare remote with a default branch
+    for repo in [&feature, &main] {
+        let remote = env.temp_dir.join(format!(
+            "{}.git",
+            repo.path.file_name().unwrap().to_string_lossy()
+        ));
+        git(
+            &env.temp_dir,
+            &[
+                "clone",
+                "--quiet",
+                "--bare",
+                repo.path.to_str().unwrap(),
+                remote.to_str().unwrap(),
+            ],
+        );
+        git(
+            &repo.path,
+            &["remote", "add", "origin", remote.to_str().unwrap()],
+        );
+        // without `refs/remotes/origin/HEAD`, the default branch is asked from the remote
+        git(&repo.path, &["fetch", "--quiet", "origin"]);
+        git(&repo.path, &["commit", "--allow-empty", "-m", "Change"]);
+    }
+    git(&feature.path, &["checkout", "--quiet", "-b", "campaign"]);
+
+    let output = env.run_nut(&["push"], Some(workspace.id));
+    assert!(
+        !output.status.success(),
+        "a refused push should fail nut push"
+    );
+    assert!(
+        String::from_utf8_lossy(&output.stderr)
+            .contains("Push failed or was refused in 1 repositories")
+    );
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        stdout.contains("pushed, upstream set to origin/campaign")
+            && stdout.contains("refused, default branch"),
+        "push should push the feature branch and refuse the default branch, got:\n{stdout}"
+    );
+    assert_eq!(
+        git(&feature.path, &["rev-parse", "--abbrev-ref", "@{upstream}"]),
+        "origin/campaign"
+    );
+
+    // nothing to push on the default branch is fine
+    let default_branch = git(&main.path, &["branch", "--show-current"]);
+    git(
+        &main.path,
+        &[
+            "branch",
+            "--set-upstream-to",
+            &format!("origin/{default_branch}"),
+        ],
+    );
+    git(&main.path, &["reset", "--quiet", "--hard", "@{upstream}"]);
+    let output = env.run_nut(&["push"], Some(workspace.id));
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        !stdout.contains("refused") && stdout.contains("up to date"),
+        "second push should have nothing to do, got:\n{stdout}"
+    );
+
+    // a remote that cannot be reached has no known default branch
+    let broken = env.create_repo(&workspace, "org", "broken");
+    let missing = env.temp_dir.join("missing.git");
+    git(
+        &broken.path,
+        &["remote", "add", "origin", missing.to_str().unwrap()],
+    );
+    git(&broken.path, &["checkout", "--quiet", "-b", "campaign"]);
+    let output = env.run_nut(&["push"], Some(workspace.id));
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stdout).contains("refused, unknown default branch"));
+
+    let output = env.run_nut(&["push", "--allow-default-branch"], Some(workspace.id));
+    assert!(
+        !output.status.success(),
+        "failed pushes should fail nut push"
+    );
+    assert!(
+        String::from_utf8_lossy(&output.stderr)
+            .contains("Push failed or was refused in 1 repositories")
+    );
+}
+
+#[test]
+fn test_grep_across_repositories() {
+    let env = TestEnv::new("grep");
+    let workspace = env.create_workspace("Grep workspace");
+    let repo_1 = env.create_repo(&workspace, "org", "repo-1");
+    let repo_2 = env.create_repo(&workspace, "org", "repo-2");
+    fs::write(repo_1.path.join("lib.rs"), "fn a() {}\nold_api();\n").unwrap();
+    fs::write(repo_2.path.join(".gitignore"), "target/\n").unwrap();
+    fs::create_dir_all(repo_2.path.join("target")).unwrap();
+    fs::write(repo_2.path.join("target/generated.rs"), "old_api();\n").unwrap();
+
+    let output = env.run_nut(&["grep", "old_api"], Some(workspace.id));
+    assert!(output.status.success());
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout),
+        "org/repo-1/lib.rs:2:old_api();\n",
+        "grep should prefix matches with the repository and skip ignored files"
+    );
+
+    let output = env.run_nut(&["grep", "-l", "-i", "TEST REPO"], Some(workspace.id));
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout),
+        "org/repo-1/README.md\norg/repo-2/README.md\n"
+    );
+
+    let output = env.run_nut(&["grep", "old_api", "--format", "json"], Some(workspace.id));
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(json[0]["repo"], "org/repo-1");
+    assert_eq!(json[0]["line"], 2);
+}
+
+#[test]
+fn test_replace_across_repositories() {
+    let env = TestEnv::new("replace");
+    let workspace = env.create_workspace("Replace workspace");
+    let repo = env.create_repo(&workspace, "org", "repo");
+    fs::write(
+        repo.path.join("ci.yaml"),
+        "image: node:18\nother: node:18\n",
+    )
+    .unwrap();
+    fs::write(repo.path.join("notes.txt"), "node:18\n").unwrap();
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(repo.path.join("ci.yaml"), fs::Permissions::from_mode(0o750)).unwrap();
+    }
+
+    let args = [
+        "replace",
+        r"node:(\d+)",
+        "node:${1}-alpine",
+        "--glob",
+        "*.yaml",
+    ];
+    let mut dry_run = args.to_vec();
+    dry_run.push("--dry-run");
+    let output = env.run_nut(&dry_run, Some(workspace.id));
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        stdout.contains("+image: node:18-alpine")
+            && stdout.contains("org/repo: 2 replacement(s) in 1 file(s)"),
+        "dry run should preview the changes, got:\n{stdout}"
+    );
+    assert!(
+        fs::read_to_string(repo.path.join("ci.yaml"))
+            .unwrap()
+            .contains("node:18\n")
+    );
+
+    let output = env.run_nut(&args, Some(workspace.id));
+    assert!(
+        output.status.success(),
+        "replace should succeed: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    assert_eq!(
+        fs::read_to_string(repo.path.join("ci.yaml")).unwrap(),
+        "image: node:18-alpine\nother: node:18-alpine\n"
+    );
+    assert_eq!(
+        fs::read_to_string(repo.path.join("notes.txt")).unwrap(),
+        "node:18\n"
+    );
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        let mode = fs::metadata(repo.path.join("ci.yaml"))
+            .unwrap()
+            .permissions()
+            .mode();
+        assert_eq!(mode & 0o777, 0o750);
+    }
+}
+
+#[test]
+fn test_edit_json_across_repositories() {
+    let env = TestEnv::new("edit");
+    let workspace = env.create_workspace("Edit workspace");
+    let with_file = env.create_repo(&workspace, "org", "node");
+    let other = env.create_repo(&workspace, "org", "other");
+    fs::write(
+        with_file.path.join("package.json"),
+        "{\n  \"name\": \"node\",\n  \"engines\": {\n    \"node\": \">=18\"\n  }\n}\n",
+    )
+    .unwrap();
+
+    let output = env.run_nut(
+        &[
+            "edit",
+            "--file",
+            "package.json",
+            "--set",
+            r#".engines.node=">=20""#,
+            "--set",
+            ".private=true",
+        ],
+        Some(workspace.id),
+    );
+    assert!(
+        output.status.success(),
+        "edit should succeed: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        stdout.contains("org/node: changed") && stdout.contains("1 without the file"),
+        "edit should report changed and skipped repositories, got:\n{stdout}"
+    );
+    assert_eq!(
+        fs::read_to_string(with_file.path.join("package.json")).unwrap(),
+        "{\n  \"name\": \"node\",\n  \"engines\": {\n    \"node\": \">=20\"\n  },\n  \"private\": true\n}\n"
+    );
+    assert!(!other.path.join("package.json").exists());
+
+    // A file that cannot be edited fails the command
+    fs::write(other.path.join("package.json"), "not json\n").unwrap();
+    let output = env.run_nut(
+        &["edit", "--file", "package.json", "--set", ".private=false"],
+        Some(workspace.id),
+    );
+    assert!(!output.status.success(), "edit should fail");
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        stdout.contains("org/other: failed") && stdout.contains("1 failed"),
+        "got:\n{stdout}"
+    );
+    assert!(
+        fs::read_to_string(with_file.path.join("package.json"))
+            .unwrap()
+            .contains("\"private\": false")
+    );
+}
+
+#[test]
+#[cfg(unix)]
+fn test_apply_conditions() {
+    let env = TestEnv::new("apply_conditions");
+    let workspace = env.create_workspace("Conditions workspace");
+    let rust = env.create_repo(&workspace, "org", "rust");
+    let docker = env.create_repo(&workspace, "org", "docker");
+    env.create_repo(&workspace, "org", "plain");
+    fs::write(rust.path.join("Cargo.toml"), "").unwrap();
+    fs::write(rust.path.join("Dockerfile"), "").unwrap();
+    fs::write(docker.path.join("Dockerfile"), "").unwrap();
+
+    let output = env.run_nut(
+        &["apply", "--if-file", "Dockerfile", "--", "touch", "ran"],
+        Some(workspace.id),
+    );
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        stdout.contains("2 succeeded, 0 failed, 1 skipped"),
+        "repositories without the file should be skipped, got:\n{stdout}"
+    );
+    assert!(stdout.contains("Skipped, condition not met: Dockerfile exists"));
+
+    // All conditions must hold
+    let output = env.run_nut(
+        &[
+            "apply",
+            "--if-file",
+            "Dockerfile",
+            "--if",
+            "test -f Cargo.toml",
+            "--",
+            "touch",
+            "both",
+        ],
+        Some(workspace.id),
+    );
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        stdout.contains("1 succeeded, 0 failed, 2 skipped"),
+        "got:\n{stdout}"
+    );
+    assert!(rust.path.join("both").exists());
+    assert!(!docker.path.join("both").exists());
+
+    let run_id = stdout
+        .lines()
+        .find_map(|l| l.strip_prefix("Run "))
+        .and_then(|l| l.split(':').next())
+        .unwrap()
+        .to_string();
+    let output = env.run_nut(&["runs", "show", &run_id], Some(workspace.id));
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        stdout.contains("org/docker: skipped"),
+        "skipped repositories should be recorded, got:\n{stdout}"
+    );
+    let output = env.run_nut(&["runs", "show", &run_id, "org/docker"], Some(workspace.id));
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout),
+        "org/docker: skipped (condition not met: test -f Cargo.toml)\n"
+    );
+
+    // A rerun of failed repositories keeps the conditions of the original run
+    let output = env.run_nut(
+        &[
+            "apply",
+            "--if-file",
+            "Dockerfile",
+            "--",
+            "sh",
+            "-c",
+            "exit 1",
+        ],
+        Some(workspace.id),
+    );
+    assert!(String::from_utf8_lossy(&output.stdout).contains("0 succeeded, 2 failed"));
+    fs::remove_file(docker.path.join("Dockerfile")).unwrap();
+    let output = env.run_nut(&["apply", "--rerun-failed"], Some(workspace.id));
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        stdout.contains("0 succeeded, 1 failed, 1 skipped"),
+        "the rerun should skip repositories without the file, got:\n{stdout}"
+    );
+}
+
+#[test]
+#[cfg(unix)]
+fn test_apply_shell() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let env = TestEnv::new("apply_shell");
+    let workspace = env.create_workspace("Shell workspace");
+    let repo = env.create_repo(&workspace, "org", "repo");
+
+    let output = env.run_nut(&["config", "--shell", "/bin/sh"], None);
+    assert!(output.status.success());
+
+    let output = env.run_nut(
+        &[
+            "apply",
+            "--shell",
+            r#"echo "repo: $NUT_REPO" | tr a-z A-Z && test "$NUT_REPO_DIR" = "$PWD""#,
+        ],
+        Some(workspace.id),
+    );
+    assert!(
+        output.status.success(),
+        "apply with shell should succeed: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        stdout.contains("REPO: ORG/REPO") && stdout.contains("1 succeeded, 0 failed"),
+        "shell command should see the repository, got:\n{stdout}"
+    );
+
+    // Scripts get the same environment
+    let script = env.temp_dir.join("env.sh");
+    fs::write(
+        &script,
+        "#!/bin/sh\necho \"$NUT_WORKSPACE_ID $NUT_WORKSPACE_DIR\" > env.txt\n",
+    )
+    .unwrap();
+    fs::set_permissions(&script, fs::Permissions::from_mode(0o755)).unwrap();
+    let output = env.run_nut(
+        &["apply", "--script", script.to_str().unwrap()],
+        Some(workspace.id),
+    );
+    assert!(output.status.success());
+    assert_eq!(
+        fs::read_to_string(repo.path.join("env.txt")).unwrap(),
+        format!("{} {}\n", workspace.id, workspace.path.display())
+    );
+}
+
+#[test]
+#[cfg(unix)]
+fn test_apply_timeout() {
+    let env = TestEnv::new("apply_timeout");
+    let workspace = env.create_workspace("Timeout workspace");
+    env.create_repo(&workspace, "org", "repo");
+
+    let started = std::time::Instant::now();
+    let output = env.run_nut(
+        &["apply", "--timeout", "1s", "--", "sleep", "30"],
+        Some(workspace.id),
+    );
+    assert!(started.elapsed() < std::time::Duration::from_secs(10));
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stdout.contains("0 succeeded, 1 failed") && stderr.contains("Timed out after 1s"),
+        "timed out commands should fail, got:\n{stdout}\n{stderr}"
+    );
+}
+
+#[test]
+#[cfg(unix)]
+fn test_apply_terminated() {
+    let env = TestEnv::new("apply_terminated");
+    let workspace = env.create_workspace("Terminated workspace");
+    env.create_repo(&workspace, "org", "repo-1");
+    env.create_repo(&workspace, "org", "repo-2");
+
+    let child = Command::new(TestEnv::nut_binary())
+        .args(["apply", "--", "sh", "-c", "sleep 30"])
+        .current_dir(env.nut_working_directory(Some(workspace.id)))
+        .env("HOME", &env.temp_dir)
+        .stdout(std::process::Stdio::piped())
+        .stderr(std::process::Stdio::piped())
+        .spawn()
+        .unwrap();
+    std::thread::sleep(std::time::Duration::from_secs(1));
+    Command::new("kill")
+        .args(["-TERM", &child.id().to_string()])
+        .status()
+        .unwrap();
+
+    // SIGTERM is forwarded to the command and aborts the run
+    let output = child.wait_with_output().unwrap();
+    assert!(!output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        stdout.contains("0 succeeded, 1 failed, 1 not run")
+            && stdout.contains("Not run: org/repo-2"),
+        "remaining repositories should be reported, got:\n{stdout}"
+    );
+    assert!(String::from_utf8_lossy(&output.stderr).contains("was aborted"));
+
+    // terminating the command in the last repository aborts the run as well
+    let child = Command::new(TestEnv::nut_binary())
+        .args([
+            "apply",
+            "--shell",
+            "test $NUT_REPO = org/repo-1 || sleep 30",
+        ])
+        .current_dir(env.nut_working_directory(Some(workspace.id)))
+        .env("HOME", &env.temp_dir)
+        .stdout(std::process::Stdio::piped())
+        .stderr(std::process::Stdio::piped())
+        .spawn()
+        .unwrap();
+    std::thread::sleep(std::time::Duration::from_secs(1));
+    Command::new("kill")
+        .args(["-TERM", &child.id().to_string()])
+        .status()
+        .unwrap();
+    let output = child.wait_with_output().unwrap();
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stdout).contains("1 succeeded, 1 failed"));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("was aborted"));
+}
+
+#[test]
+fn test_apply_passes_stdin() {
+    let env = TestEnv::new("apply_stdin");
+    let workspace = env.create_workspace("Stdin workspace");
+    env.create_repo(&workspace, "org", "repo");
+
+    // interactive commands can read from the terminal, unless confirmations need it
+    let output = env.run_nut_with_input(
+        &["apply", "--shell", "read answer && echo \"got $answer\""],
+        Some(workspace.id),
+        "yes\n",
+    );
+    assert!(output.status.success());
+    assert!(String::from_utf8_lossy(&output.stdout).contains("got yes"));
+
+    let output = env.run_nut_with_input(
+        &["apply", "--timeout", "10s", "--shell", "read answer"],
+        Some(workspace.id),
+        "yes\n",
+    );
+    assert!(String::from_utf8_lossy(&output.stdout).contains("0 succeeded, 1 failed"));
+}
+
+#[test]
+#[cfg(unix)]
+fn test_apply_confirm() {
+    let env = TestEnv::new("apply_confirm");
+    let workspace = env.create_workspace("Confirm workspace");
+    let repo_1 = env.create_repo(&workspace, "org", "repo-1");
+    let repo_2 = env.create_repo(&workspace, "org", "repo-2");
+    let repo_3 = env.create_repo(&workspace, "org", "repo-3");
+
+    let output = env.run_nut_with_input(
+        &["apply", "--confirm", "--", "touch", "confirmed"],
+        Some(workspace.id),
+        "n\ny\nq\n",
+    );
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        stdout.contains("Run 'touch confirmed' here? [y,n,a,q]")
+            && stdout.contains("1 succeeded, 0 failed, 2 skipped"),
+        "got:\n{stdout}"
+    );
+    assert!(!repo_1.path.join("confirmed").exists());
+    assert!(repo_2.path.join("confirmed").exists());
+    assert!(!repo_3.path.join("confirmed").exists());
+}
+
+#[test]
+#[cfg(unix)]
+fn test_apply_confirm_after_diff() {
+    let env = TestEnv::new("apply_confirm_after_diff");
+    let workspace = env.create_workspace("Confirm after diff workspace");
+    let repo_1 = env.create_repo(&workspace, "org", "repo-1");
+    let repo_2 = env.create_repo(&workspace, "org", "repo-2");
+    let repo_3 = env.create_repo(&workspace, "org", "repo-3");
+    // uncommitted changes would be lost when reverting
+    fs::write(repo_3.path.join("README.md"), "local change\n").unwrap();
+
+    let output = env.run_nut_with_input(
+        &[
+            "apply",
+            "--confirm-after-diff",
+            "--shell",
+            "echo changed >> README.md && touch new-file",
+        ],
+        Some(workspace.id),
+        "y\nn\n",
+    );
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        stdout.contains("README.md | 1 +")
+            && stdout.contains("new-file (new)")
+            && stdout.contains("Changes reverted")
+            && stdout.contains("Skipped, repository has uncommitted changes")
+            && stdout.contains("2 succeeded, 0 failed, 1 skipped"),
+        "got:\n{stdout}"
+    );
+
+    assert!(repo_1.path.join("new-file").exists());
+    assert_eq!(
+        fs::read_to_string(repo_2.path.join("README.md")).unwrap(),
+        "# Test Repo\n"
+    );
+    assert!(!repo_2.path.join("new-file").exists());
+    assert_eq!(
+        fs::read_to_string(repo_3.path.join("README.md")).unwrap(),
+        "local change\n"
+    );
+
+    // commits made by the command are shown and reverted as well
+    fs::write(repo_3.path.join("README.md"), "# Test Repo\n").unwrap();
+    let head = git(&repo_2.path, &["rev-parse", "HEAD"]);
+    let output = env.run_nut_with_input(
+        &[
+            "apply",
+            "--confirm-after-diff",
+            "--shell",
+            "echo committed >> README.md && git commit --quiet -am 'Commit by command'",
+        ],
+        Some(workspace.id),
+        "n\nn\nn\n",
+    );
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        stdout.contains("Commit by command") && stdout.contains("README.md | 1 +"),
+        "got:\n{stdout}"
+    );
+    assert_eq!(git(&repo_2.path, &["rev-parse", "HEAD"]), head);
+    assert_eq!(
+        fs::read_to_string(repo_2.path.join("README.md")).unwrap(),
+        "# Test Repo\n"
+    );
+}
+
+#[test]
+#[cfg(unix)]
+fn test_apply_log_dir() {
+    let env = TestEnv::new("apply_log_dir");
+    let workspace = env.create_workspace("Log dir workspace");
+    env.create_repo(&workspace, "org", "repo-1");
+    env.create_repo(&workspace, "org", "repo-2");
+
+    let log_dir = env.temp_dir.join("logs");
+    let output = env.run_nut(
+        &[
+            "apply",
+            "--log-dir",
+            log_dir.to_str().unwrap(),
+            "--shell",
+            "echo lots of output; test $NUT_REPO = org/repo-1",
+        ],
+        Some(workspace.id),
+    );
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        !stdout.contains("lots of output") && !stdout.contains("==>"),
+        "output should only go to the log files, got:\n{stdout}"
+    );
+    assert!(stdout.contains("org/repo-1: ok after"), "got:\n{stdout}");
+    assert!(
+        stdout.contains("org/repo-2: failed after")
+            && stdout.contains(&log_dir.join("org/repo-2.log").display().to_string()),
+        "got:\n{stdout}"
+    );
+    assert_eq!(
+        fs::read_to_string(log_dir.join("org/repo-1.log")).unwrap(),
+        "lots of output\n"
+    );
+
+    // runs show finds the logs in the custom directory
+    let run_id = stdout
+        .lines()
+        .find_map(|l| l.strip_prefix("Run "))
+        .and_then(|l| l.split(':').next())
+        .unwrap()
+        .to_string();
+    let output = env.run_nut(&["runs", "show", &run_id, "org/repo-2"], Some(workspace.id));
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "lots of output\n");
+}
+
+#[test]
+#[cfg(unix)]
+fn test_check() {
+    let env = TestEnv::new("check");
+    let workspace = env.create_workspace("Check workspace");
+    let passing = env.create_repo(&workspace, "org", "passing");
+    let failing = env.create_repo(&workspace, "org", "failing");
+    env.create_repo(&workspace, "org", "unknown");
+    fs::write(passing.path.join("Cargo.toml"), "").unwrap();
+    fs::write(passing.path.join("ok"), "").unwrap();
+    fs::write(failing.path.join("Cargo.toml"), "").unwrap();
+
+    let output = env.run_nut(
+        &[
+            "config",
+            "--check-command",
+            "rust=echo checking && test -f ok",
+        ],
+        None,
+    );
+    assert!(output.status.success());
+    let output = env.run_nut(&["config", "--check-command", "cobol=make"], None);
+    assert!(!output.status.success());
+
+    let junit = env.temp_dir.join("junit.xml");
+    let output = env.run_nut(
+        &["check", "--junit", junit.to_str().unwrap()],
+        Some(workspace.id),
+    );
+    assert!(
+        !output.status.success(),
+        "failed checks should fail nut check"
+    );
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let line = |repo: &str| {
+        stdout
+            .lines()
+            .find(|l| l.starts_with(repo) && !l.contains("..."))
+            .unwrap_or_default()
+            .to_string()
+    };
+    assert!(line("org/passing").contains("rust") && line("org/passing").ends_with("passed"));
+    assert!(
+        line("org/failing").contains("FAILED, see"),
+        "got:\n{stdout}"
+    );
+    assert!(line("org/unknown").contains("skipped, no known ecosystem"));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("Checks failed in 1 repositories"));
+
+    let xml = fs::read_to_string(&junit).unwrap();
+    assert!(
+        xml.contains(r#"tests="3" failures="1" skipped="1""#),
+        "got:\n{xml}"
+    );
+    assert!(xml.contains(r#"<testcase name="org/failing" classname="rust""#));
+    assert!(xml.contains("checking</failure>"), "got:\n{xml}");
+}
+
+#[test]
+fn test_status_ecosystems() {
+    let env = TestEnv::new("status_ecosystems");
+    let workspace = env.create_workspace("Ecosystem workspace");
+    let rust = env.create_repo(&workspace, "org", "rust");
+    let node = env.create_repo(&workspace, "org", "node");
+    env.create_repo(&workspace, "org", "docs");
+    fs::write(rust.path.join("Cargo.toml"), "").unwrap();
+    fs::write(rust.path.join("Makefile"), "").unwrap();
+    fs::write(node.path.join("package.json"), "{}").unwrap();
+    git(&rust.path, &["add", "."]);
+    git(&rust.path, &["commit", "-m", "manifests"]);
+
+    let output = env.run_nut(&["status", "--long"], Some(workspace.id));
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        stdout.contains("org/rust (")
+            && stdout.contains("    rust, make (Cargo.toml, Makefile)")
+            && stdout.contains("    node (package.json)")
+            && stdout.contains("    no known ecosystem"),
+        "got:\n{stdout}"
+    );
+
+    let output = env.run_nut(&["status", "--format", "json"], Some(workspace.id));
+    assert!(output.status.success());
+    let repos: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    let rust_entry = repos
+        .as_array()
+        .unwrap()
+        .iter()
+        .find(|r| r["path"] == "org/rust")
+        .unwrap();
+    assert_eq!(
+        rust_entry["ecosystems"],
+        serde_json::json!(["rust", "make"])
+    );
+    assert_eq!(rust_entry["has_changes"], false);
+
+    let output = env.run_nut(
+        &["apply", "--ecosystem", "rust,node", "--", "touch", "marker"],
+        Some(workspace.id),
+    );
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        stdout.contains("2 succeeded, 0 failed, 1 skipped")
+            && stdout.contains("condition not met: ecosystem is rust or node"),
+        "got:\n{stdout}"
+    );
+}
+
+#[test]
+fn test_deps() {
+    let env = TestEnv::new("deps");
+    let workspace = env.create_workspace("Deps workspace");
+    let web = env.create_repo(&workspace, "org", "web");
+    let admin = env.create_repo(&workspace, "org", "admin");
+    let service = env.create_repo(&workspace, "org", "service");
+    fs::write(
+        web.path.join("package-lock.json"),
+        r#"{"lockfileVersion": 3, "packages": {
+            "": {"name": "web"},
+            "node_modules/lodash": {"version": "4.17.20"}
+        }}"#,
+    )
+    .unwrap();
+    fs::write(
+        admin.path.join("package-lock.json"),
+        r#"{"lockfileVersion": 1, "dependencies": {
+            "lodash": {"version": "4.17.21"}
+        }}"#,
+    )
+    .unwrap();
+    fs::write(
+        service.path.join("Cargo.lock"),
+        "[[package]]\nname = \"service\"\nversion = \"0.1.0\"\n\n\
+         [[package]]\nname = \"serde\"\nversion = \"1.0.200\"\n\
+         source = \"registry+https://github.com/rust-lang/crates.io-index\"\n",
+    )
+    .unwrap();
+    fs::write(
+        service.path.join("go.mod"),
+        "module example.com/service\n\ngo 1.21\n\nrequire (\n\tgolang.org/x/net v0.17.0\n)\n",
+    )
+    .unwrap();
+    // go.sum also lists versions that are no longer required
+    fs::write(
+        service.path.join("go.sum"),
+        "golang.org/x/net v0.10.0/go.mod h1:abc=\ngolang.org/x/net v0.17.0 h1:def=\n",
+    )
+    .unwrap();
+    fs::write(service.path.join("requirements.txt"), "requests==2.31.0\n").unwrap();
+
+    let output = env.run_nut(&["deps"], Some(workspace.id));
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        stdout.contains("lodash (node)\n  4.17.20  org/web\n  4.17.21  org/admin\n"),
+        "got:\n{stdout}"
+    );
+    assert!(stdout.contains("serde (rust)\n  1.0.200  org/service\n"));
+    assert!(stdout.contains("golang.org/x/net (go)\n  v0.17.0  org/service\n"));
+    assert!(
+        !stdout.contains("service (rust)"),
+        "local crates are not dependencies"
+    );
+
+    let output = env.run_nut(&["deps", "why", "lodash<4.17.21"], Some(workspace.id));
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("org/web"), "got:\n{stdout}");
+    assert!(!stdout.contains("org/admin"));
+
+    let output = env.run_nut(
+        &["deps", "why", "lodash", "--format", "csv"],
+        Some(workspace.id),
+    );
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout),
+        "ecosystem,name,version,repository,file\n\
+         node,lodash,4.17.20,org/web,package-lock.json\n\
+         node,lodash,4.17.21,org/admin,package-lock.json\n"
+    );
+
+    let output = env.run_nut(
+        &["deps", "--ecosystem", "python", "--format", "json"],
+        Some(workspace.id),
+    );
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(
+        json,
+        serde_json::json!([{
+            "name": "requests",
+            "ecosystem": "python",
+            "version": "2.31.0",
+            "repository": "org/service",
+            "file": "requirements.txt"
+        }])
+    );
+
+    // Python names are matched like pip does, and the ecosystem filter applies
+    let output = env.run_nut(&["deps", "why", "Requests"], Some(workspace.id));
+    assert!(String::from_utf8_lossy(&output.stdout).contains("org/service"));
+    let output = env.run_nut(
+        &["deps", "why", "lodash", "--ecosystem", "rust"],
+        Some(workspace.id),
+    );
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout),
+        "No repository uses lodash\n"
+    );
+
+    let output = env.run_nut(&["deps", "why", "lodash<"], Some(workspace.id));
+    assert!(!output.status.success());
+}
+
+#[test]
+fn test_apply_hooks() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let env = TestEnv::new("apply_hooks");
+    let workspace = env.create_workspace("Hooks workspace");
+    env.create_repo(&workspace, "org", "repo");
+    let log = env.temp_dir.join("hooks.log");
+
+    let output = env.run_nut(
+        &[
+            "config",
+            "--hook",
+            &format!(
+                "pre-apply=echo \"$NUT_HOOK $NUT_RUN_ID\" >> {}",
+                log.display()
+            ),
+        ],
+        None,
+    );
+    assert!(output.status.success());
+    let output = env.run_nut(&["config", "--hook", "post-push=true"], None);
+    assert!(!output.status.success());
+
+    let hooks_dir = workspace.path.join(".nut").join("hooks");
+    fs::create_dir_all(&hooks_dir).unwrap();
+    let script = hooks_dir.join("post-apply");
+    fs::write(
+        &script,
+        format!(
+            "#!/bin/sh\necho \"$NUT_HOOK $NUT_RUN_STATUS in $PWD\" >> {}\n",
+            log.display()
+        ),
+    )
+    .unwrap();
+    fs::set_permissions(&script, fs::Permissions::from_mode(0o755)).unwrap();
+
+    let output = env.run_nut(&["apply", "--", "true"], Some(workspace.id));
+    assert!(output.status.success());
+    let hooks_log = fs::read_to_string(&log).unwrap();
+    let lines: Vec<_> = hooks_log.lines().collect();
+    assert_eq!(lines.len(), 2, "got:\n{hooks_log}");
+    assert!(lines[0].starts_with("pre-apply ") && lines[0].len() > "pre-apply ".len());
+    assert!(
+        lines[1].starts_with("post-apply success in ")
+            && lines[1].ends_with(&workspace.id.to_string())
+    );
+
+    // the post-apply hook gets the outcome, and its failure does not hide the summary
+    fs::write(
+        &script,
+        format!(
+            "#!/bin/sh\necho \"$NUT_HOOK $NUT_RUN_STATUS\" >> {}\nexit 1\n",
+            log.display()
+        ),
+    )
+    .unwrap();
+    let output = env.run_nut(&["apply", "--", "false"], Some(workspace.id));
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stdout).contains("0 succeeded, 1 failed"));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("Hook post-apply failed"));
+    assert!(
+        fs::read_to_string(&log)
+            .unwrap()
+            .ends_with("post-apply failed\n")
+    );
+    fs::remove_file(&script).unwrap();
+
+    // a failing pre-apply hook stops apply before any repository
+    let output = env.run_nut(&["config", "--hook", "pre-apply=exit 3"], None);
+    assert!(output.status.success());
+    let output = env.run_nut(&["apply", "--", "touch", "applied"], Some(workspace.id));
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("Hook pre-apply failed"));
+    assert!(!workspace.path.join("org/repo/applied").exists());
+
+    let output = env.run_nut(&["config", "--hook", "pre-apply="], None);
+    assert!(output.status.success());
+    let output = env.run_nut(&["apply", "--", "touch", "applied"], Some(workspace.id));
+    assert!(output.status.success());
+    assert!(workspace.path.join("org/repo/applied").exists());
+}
+
+#[test]
+fn test_git_config_overrides() {
+    let env = TestEnv::new("git_config_overrides");
+    let workspace = env.create_workspace("Git config workspace");
+    let repo = env.create_repo(&workspace, "org", "repo");
+    let get = |key: &str| {
+        let output = Command::new("git")
+            .args(["config", "--get", key])
+            .current_dir(&repo.path)
+            .output()
+            .unwrap();
+        String::from_utf8_lossy(&output.stdout).trim().to_string()
+    };
+
+    let output = env.run_nut(&["doctor"], Some(workspace.id));
+    assert!(output.status.success());
+    assert!(String::from_utf8_lossy(&output.stdout).contains("No git config overrides"));
+
+    let output = env.run_nut(
+        &[
+            "config",
+            "--git-config",
+            "user.email=me@home.example",
+            "--git-config",
+            "core.hooksPath=.githooks",
+        ],
+        None,
+    );
+    assert!(output.status.success());
+    let output = env.run_nut(
+        &[
+            "config",
+            "--git-config",
+            "user.email=me@work.example",
+            "--workspace",
+            &workspace.id.to_string(),
+        ],
+        None,
+    );
+    assert!(output.status.success());
+    let output = env.run_nut(&["config", "--git-config", "email"], None);
+    assert!(!output.status.success());
+
+    let output = env.run_nut(&["doctor"], Some(workspace.id));
+    assert!(!output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        stdout.contains("org/repo: user.email is 'test@example.com' instead of 'me@work.example'"),
+        "got:\n{stdout}"
+    );
+    assert!(stdout.contains("org/repo: core.hookspath is not set instead of '.githooks'"));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("nut doctor --fix"));
+
+    let output = env.run_nut(&["doctor", "--fix"], Some(workspace.id));
+    assert!(output.status.success());
+    assert_eq!(get("user.email"), "me@work.example");
+    assert_eq!(get("core.hooksPath"), ".githooks");
+
+    let output = env.run_nut(&["doctor"], Some(workspace.id));
+    assert!(output.status.success());
+    assert!(String::from_utf8_lossy(&output.stdout).contains("All 1 repositories use"));
+
+    // removing the workspace override falls back to the one for all workspaces
+    let output = env.run_nut(
+        &[
+            "config",
+            "--git-config",
+            "user.email=",
+            "--workspace",
+            &workspace.id.to_string(),
+        ],
+        None,
+    );
+    assert!(output.status.success());
+    let output = env.run_nut(&["doctor", "--fix"], Some(workspace.id));
+    assert!(output.status.success());
+    assert_eq!(get("user.email"), "me@home.example");
+}